@@ -0,0 +1,91 @@
+// Compara el recorrido lineal de los objetos contra la BVH.
+// Ejecutar con `cargo bench --bench bvh`.
+
+use minecraft_diorama::scene_file::parse_scene;
+use minecraft_diorama::{render, Camera, Color, Cube, Framebuffer, Light, Material, Scene};
+use nalgebra::Vector3;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn time_render(scene: &Scene, width: usize, height: usize, frames: u32) -> (Duration, Framebuffer) {
+    let mut framebuffer = Framebuffer::new(width, height);
+    render(&mut framebuffer, scene);
+
+    let start = Instant::now();
+    for _ in 0..frames {
+        render(&mut framebuffer, scene);
+    }
+    (start.elapsed() / frames, framebuffer)
+}
+
+fn compare(name: &str, scene: &mut Scene, width: usize, height: usize, frames: u32) {
+    scene.clear_bvh();
+    let (linear, linear_image) = time_render(scene, width, height, frames);
+
+    let build_start = Instant::now();
+    scene.build_bvh();
+    let build = build_start.elapsed();
+    let (bvh, bvh_image) = time_render(scene, width, height, frames);
+
+    assert_eq!(linear_image.buffer, bvh_image.buffer, "la BVH cambió la imagen de {}", name);
+
+    println!(
+        "{:<24} {:>6} objetos {}x{}: lineal {:>10.2?}  bvh {:>10.2?} (construcción {:.2?})  x{:.1}",
+        name,
+        scene.objects.len(),
+        width,
+        height,
+        linear,
+        bvh,
+        build,
+        linear.as_secs_f64() / bvh.as_secs_f64()
+    );
+}
+
+// Terreno de bloques de colores para medir escenas grandes
+fn terrain(size: i32) -> Scene {
+    let grass = Arc::new(Material::new(Color::new(90, 160, 60), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, false, None));
+    let dirt = Arc::new(Material::new(Color::new(120, 85, 50), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, false, None));
+
+    let camera = Camera::new(
+        Vector3::new(0.0, size as f32 * 0.6, -(size as f32)),
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    let lights = vec![Light::new(Vector3::new(100.0, 100.0, -100.0), Color::new(255, 255, 255), 2.0, 5.0)];
+    let mut scene = Scene::new(Vec::new(), lights, camera);
+
+    for z in -size / 2..size / 2 {
+        for x in -size / 2..size / 2 {
+            let height = (((x as f32) * 0.3).sin() * ((z as f32) * 0.2).cos() * 2.0 + 2.0) as i32;
+            for y in 0..=height {
+                let material = if y == height { &grass } else { &dirt };
+                scene.add_object(Cube::new(
+                    Vector3::new(x as f32, y as f32, z as f32),
+                    1.0,
+                    std::array::from_fn(|_| Arc::clone(material)),
+                ));
+            }
+        }
+    }
+    scene
+}
+
+// El diorama con cada bloque como un cubo suelto: con `voxel_grid` casi todo queda en un solo
+// objeto y la BVH no tendría nada que ordenar
+fn cube_diorama() -> Scene {
+    let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let source = std::fs::read_to_string(assets.join("diorama.json")).expect("no se pudo leer el diorama");
+    let mut desc: serde_json::Value = serde_json::from_str(&source).expect("el diorama no es JSON válido");
+    desc["voxel_grid"] = serde_json::Value::Bool(false);
+    parse_scene(&desc.to_string(), &assets).expect("no se pudo cargar el diorama")
+}
+
+fn main() {
+    let mut diorama = cube_diorama();
+    compare("diorama (cubos)", &mut diorama, 200, 150, 5);
+
+    let mut large = terrain(96);
+    compare("terreno 96x96", &mut large, 64, 48, 1);
+}
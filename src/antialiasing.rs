@@ -0,0 +1,427 @@
+use rayon::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+use crate::radiance::Radiance;
+use crate::sampling::Sampler;
+
+// Muestras por lado de píxel si no se indica otra cosa (3x3 = 9 rayos)
+pub const DEFAULT_SUPERSAMPLES: u32 = 3;
+// Contraste relativo entre vecinos a partir del cual el muestreo adaptativo refina un píxel
+pub const DEFAULT_ADAPTIVE_THRESHOLD: f32 = 0.25;
+
+// Dónde caen los rayos de cada píxel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplePattern {
+    // Un rayo por el centro del píxel (el render de siempre)
+    Center,
+    // `n` x `n` rayos en una cuadrícula regular
+    Grid { n: u32 },
+    // `n` x `n` rayos, cada uno en un punto al azar de su celda
+    Stratified { n: u32 },
+    // Un rayo por el centro, y `n` x `n` estratificados más en los píxeles cuyo brillo difiere
+    // del de algún vecino en más de `threshold` (bordes de bloques, sombras, texturas)
+    Adaptive { n: u32, threshold: f32 },
+}
+
+impl SamplePattern {
+    // Lado de la cuadrícula de muestras, 1 con un solo rayo
+    pub fn samples_per_side(&self) -> u32 {
+        match *self {
+            SamplePattern::Center => 1,
+            SamplePattern::Grid { n } | SamplePattern::Stratified { n } | SamplePattern::Adaptive { n, .. } => n,
+        }
+    }
+
+    // El mismo patrón con otro lado de cuadrícula
+    pub fn with_samples_per_side(self, n: u32) -> Self {
+        let n = n.max(1);
+        match self {
+            SamplePattern::Center => SamplePattern::Center,
+            SamplePattern::Grid { .. } => SamplePattern::Grid { n },
+            SamplePattern::Stratified { .. } => SamplePattern::Stratified { n },
+            SamplePattern::Adaptive { threshold, .. } => SamplePattern::Adaptive { n, threshold },
+        }
+    }
+
+    // Desplazamientos desde el centro del píxel, en [-0.5, 0.5)
+    fn offsets(&self, sampler: &mut Sampler) -> Vec<(f32, f32)> {
+        match *self {
+            SamplePattern::Center => vec![(0.0, 0.0)],
+            SamplePattern::Grid { n } => {
+                let n = n.max(1);
+                let cell = |i: u32| (i as f32 + 0.5) / n as f32 - 0.5;
+                (0..n).flat_map(|i| (0..n).map(move |j| (cell(i), cell(j)))).collect()
+            }
+            SamplePattern::Stratified { n } | SamplePattern::Adaptive { n, .. } => sampler
+                .stratified(n.max(1) * n.max(1))
+                .into_iter()
+                .map(|(u, v)| (u - 0.5, v - 0.5))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for SamplePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let n = DEFAULT_SUPERSAMPLES;
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "center" => Ok(SamplePattern::Center),
+            "grid" => Ok(SamplePattern::Grid { n }),
+            "stratified" => Ok(SamplePattern::Stratified { n }),
+            "adaptive" => Ok(SamplePattern::Adaptive { n, threshold: DEFAULT_ADAPTIVE_THRESHOLD }),
+            _ => Err(format!("muestreo desconocido: {} (none, grid, stratified, adaptive)", s)),
+        }
+    }
+}
+
+// Filtro de reconstrucción: cuánto pesa cada muestra en los píxeles cercanos según su
+// distancia al centro de cada uno
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFilter {
+    #[default]
+    Box,      // Promedio de las muestras del propio píxel
+    Tent,     // Triángulo de radio 1 píxel: mezcla un poco con los vecinos
+    Mitchell, // Mitchell-Netravali (B = C = 1/3), radio 2: suave sin perder nitidez
+}
+
+impl PixelFilter {
+    // Radio del filtro en píxeles
+    pub fn radius(&self) -> f32 {
+        match self {
+            PixelFilter::Box => 0.5,
+            PixelFilter::Tent => 1.0,
+            PixelFilter::Mitchell => 2.0,
+        }
+    }
+
+    // Peso de una muestra a (dx, dy) píxeles del centro; los filtros son separables
+    pub fn weight(&self, dx: f32, dy: f32) -> f32 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, d: f32) -> f32 {
+        let x = d.abs();
+        match self {
+            PixelFilter::Box => {
+                if x < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            PixelFilter::Tent => (1.0 - x).max(0.0),
+            PixelFilter::Mitchell => {
+                const B: f32 = 1.0 / 3.0;
+                const C: f32 = 1.0 / 3.0;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * B - 6.0 * C) * x * x * x + (-18.0 + 12.0 * B + 6.0 * C) * x * x + (6.0 - 2.0 * B)) / 6.0
+                } else if x < 2.0 {
+                    ((-B - 6.0 * C) * x * x * x + (6.0 * B + 30.0 * C) * x * x + (-12.0 * B - 48.0 * C) * x + (8.0 * B + 24.0 * C))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for PixelFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "box" => Ok(PixelFilter::Box),
+            "tent" | "triangle" => Ok(PixelFilter::Tent),
+            "mitchell" => Ok(PixelFilter::Mitchell),
+            _ => Err(format!("filtro desconocido: {} (box, tent, mitchell)", s)),
+        }
+    }
+}
+
+impl fmt::Display for PixelFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PixelFilter::Box => "box",
+            PixelFilter::Tent => "tent",
+            PixelFilter::Mitchell => "mitchell",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Una muestra y su desplazamiento desde el centro de su píxel
+#[derive(Clone, Copy)]
+struct PixelSample {
+    dx: f32,
+    dy: f32,
+    radiance: Radiance,
+}
+
+// Muestras de una fila: las del píxel x son `samples[starts[x]..starts[x + 1]]`
+struct RowSamples {
+    samples: Vec<PixelSample>,
+    starts: Vec<usize>,
+}
+
+impl RowSamples {
+    fn pixel(&self, x: usize) -> &[PixelSample] {
+        &self.samples[self.starts[x]..self.starts[x + 1]]
+    }
+}
+
+// Radiancia filtrada de cada píxel. `trace(x, y, sampler)` devuelve la radiancia del rayo que
+// pasa por el punto (x, y) de la pantalla, en píxeles; el sampler de cada píxel sale de
+// `(x, y, pass)`, así que el resultado no depende de los hilos y cambia entre pasadas
+pub(crate) fn supersample<F>(
+    width: usize,
+    height: usize,
+    pattern: SamplePattern,
+    filter: PixelFilter,
+    pass: u32,
+    trace: F,
+) -> Vec<Radiance>
+where
+    F: Fn(f32, f32, &mut Sampler) -> Radiance + Sync,
+{
+    let rows: Vec<RowSamples> = match pattern {
+        SamplePattern::Adaptive { threshold, .. } => {
+            // El primer rayo pasa por el centro en la primera pasada y por un punto al azar del
+            // píxel en las siguientes, así la acumulación también suaviza los píxeles sin refinar
+            let centers: Vec<PixelSample> = (0..height)
+                .into_par_iter()
+                .flat_map_iter(|y| {
+                    let trace = &trace;
+                    (0..width).map(move |x| {
+                        let mut sampler = Sampler::for_pixel(x, y, pass);
+                        let (dx, dy) = if pass == 0 { (0.0, 0.0) } else { (sampler.next_f32() - 0.5, sampler.next_f32() - 0.5) };
+                        PixelSample { dx, dy, radiance: trace(x as f32 + dx, y as f32 + dy, &mut sampler) }
+                    })
+                })
+                .collect();
+            (0..height)
+                .into_par_iter()
+                .map(|y| {
+                    sample_row(width, |x, samples| {
+                        samples.push(centers[y * width + x]);
+                        if needs_refinement(&centers, width, height, x, y, threshold) {
+                            // Otra secuencia que la del rayo del centro
+                            let mut sampler = Sampler::new(Sampler::for_pixel(x, y, pass).next_u64());
+                            push_samples(&pattern, x, y, &mut sampler, &trace, samples);
+                        }
+                    })
+                })
+                .collect()
+        }
+        _ => (0..height)
+            .into_par_iter()
+            .map(|y| {
+                sample_row(width, |x, samples| {
+                    let mut sampler = Sampler::for_pixel(x, y, pass);
+                    push_samples(&pattern, x, y, &mut sampler, &trace, samples);
+                })
+            })
+            .collect(),
+    };
+
+    // Cada píxel reúne las muestras de los vecinos que caen dentro del radio del filtro
+    let reach = (filter.radius() + 0.5).ceil() as isize;
+    (0..height)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let rows = &rows;
+            (0..width).map(move |x| {
+                let mut sum = Radiance::BLACK;
+                let mut total = 0.0;
+                for j in -reach..=reach {
+                    let ny = y as isize + j;
+                    if ny < 0 || ny >= height as isize {
+                        continue;
+                    }
+                    let row = &rows[ny as usize];
+                    for i in -reach..=reach {
+                        let nx = x as isize + i;
+                        if nx < 0 || nx >= width as isize {
+                            continue;
+                        }
+                        for sample in row.pixel(nx as usize) {
+                            let weight = filter.weight(i as f32 + sample.dx, j as f32 + sample.dy);
+                            if weight != 0.0 {
+                                sum += sample.radiance.scale(weight);
+                                total += weight;
+                            }
+                        }
+                    }
+                }
+                if total > 1e-6 {
+                    let color = sum.scale(1.0 / total);
+                    // Los lóbulos negativos de Mitchell pueden dar valores negativos junto a bordes muy contrastados
+                    Radiance::new(color.r.max(0.0), color.g.max(0.0), color.b.max(0.0))
+                } else {
+                    let own = rows[y].pixel(x);
+                    own.iter().fold(Radiance::BLACK, |acc, s| acc + s.radiance).scale(1.0 / own.len() as f32)
+                }
+            })
+        })
+        .collect()
+}
+
+fn sample_row(width: usize, mut fill: impl FnMut(usize, &mut Vec<PixelSample>)) -> RowSamples {
+    let mut samples = Vec::new();
+    let mut starts = Vec::with_capacity(width + 1);
+    for x in 0..width {
+        starts.push(samples.len());
+        fill(x, &mut samples);
+    }
+    starts.push(samples.len());
+    RowSamples { samples, starts }
+}
+
+fn push_samples<F>(pattern: &SamplePattern, x: usize, y: usize, sampler: &mut Sampler, trace: &F, samples: &mut Vec<PixelSample>)
+where
+    F: Fn(f32, f32, &mut Sampler) -> Radiance,
+{
+    for (dx, dy) in pattern.offsets(sampler) {
+        let radiance = trace(x as f32 + dx, y as f32 + dy, sampler);
+        samples.push(PixelSample { dx, dy, radiance });
+    }
+}
+
+// Contraste relativo de la luminancia entre el píxel y sus cuatro vecinos
+fn needs_refinement(centers: &[PixelSample], width: usize, height: usize, x: usize, y: usize, threshold: f32) -> bool {
+    let own = centers[y * width + x].radiance.luminance();
+    let neighbors = [
+        (x > 0).then(|| y * width + x - 1),
+        (x + 1 < width).then(|| y * width + x + 1),
+        (y > 0).then(|| (y - 1) * width + x),
+        (y + 1 < height).then(|| (y + 1) * width + x),
+    ];
+    neighbors.into_iter().flatten().any(|i| {
+        let other = centers[i].radiance.luminance();
+        (own - other).abs() / (own + other).max(1e-4) > threshold
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn filter_weights() {
+        assert_eq!(PixelFilter::Box.weight(0.49, -0.49), 1.0);
+        assert_eq!(PixelFilter::Box.weight(0.5, 0.0), 0.0);
+
+        assert!(close(PixelFilter::Tent.weight(0.0, 0.0), 1.0));
+        assert!(close(PixelFilter::Tent.weight(0.5, 0.0), 0.5));
+        assert!(close(PixelFilter::Tent.weight(0.5, -0.5), 0.25));
+        assert_eq!(PixelFilter::Tent.weight(1.0, 0.0), 0.0);
+
+        // Mitchell con B = C = 1/3: 8/9 en el centro, 1/18 a un píxel y nada desde dos
+        let mitchell = PixelFilter::Mitchell;
+        assert!(close(mitchell.weight_1d(0.0), 8.0 / 9.0));
+        assert!(close(mitchell.weight_1d(-1.0), 1.0 / 18.0));
+        assert_eq!(mitchell.weight_1d(2.0), 0.0);
+        assert!(mitchell.weight_1d(1.5) < 0.0);
+        assert!(close(mitchell.weight(1.0, 0.0), 1.0 / 18.0 * 8.0 / 9.0));
+        // Sus pesos suman 1 en cada eje, así que no cambia el brillo
+        let steps = 4000;
+        let integral: f32 = (0..steps)
+            .map(|i| -2.0 + (i as f32 + 0.5) * 4.0 / steps as f32)
+            .map(|x| mitchell.weight_1d(x) * 4.0 / steps as f32)
+            .sum();
+        assert!(close(integral, 1.0), "{}", integral);
+    }
+
+    #[test]
+    fn grid_offsets_are_cell_centers() {
+        let mut sampler = Sampler::new(1);
+        let mut offsets = SamplePattern::Grid { n: 2 }.offsets(&mut sampler);
+        offsets.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(offsets, vec![(-0.25, -0.25), (-0.25, 0.25), (0.25, -0.25), (0.25, 0.25)]);
+        assert_eq!(SamplePattern::Center.offsets(&mut sampler), vec![(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn stratified_offsets_fall_one_per_cell() {
+        let mut sampler = Sampler::new(2);
+        let offsets = SamplePattern::Stratified { n: 3 }.offsets(&mut sampler);
+        let mut cells: Vec<(i32, i32)> = offsets
+            .iter()
+            .map(|&(dx, dy)| {
+                assert!((-0.5..0.5).contains(&dx) && (-0.5..0.5).contains(&dy));
+                (((dx + 0.5) * 3.0) as i32, ((dy + 0.5) * 3.0) as i32)
+            })
+            .collect();
+        cells.sort();
+        cells.dedup();
+        assert_eq!(cells.len(), 9);
+        // Con otra semilla caen en otros puntos
+        assert_ne!(offsets, SamplePattern::Stratified { n: 3 }.offsets(&mut Sampler::new(3)));
+    }
+
+    // Supersampling de una imagen de 8x4 con la mitad izquierda blanca, guardando cada rayo
+    fn half_white(pattern: SamplePattern, filter: PixelFilter, pass: u32) -> (Vec<Radiance>, Vec<(f32, f32)>) {
+        let rays = Mutex::new(Vec::new());
+        let image = supersample(8, 4, pattern, filter, pass, |x, y, _| {
+            rays.lock().unwrap().push((x, y));
+            if x < 3.5 {
+                Radiance::new(1.0, 1.0, 1.0)
+            } else {
+                Radiance::BLACK
+            }
+        });
+        (image, rays.into_inner().unwrap())
+    }
+
+    #[test]
+    fn adaptive_refines_only_pixels_next_to_an_edge() {
+        let pattern = SamplePattern::Adaptive { n: 2, threshold: DEFAULT_ADAPTIVE_THRESHOLD };
+        let (image, rays) = half_white(pattern, PixelFilter::Box, 0);
+        // Un rayo por píxel más 4 en las columnas 3 y 4 de cada fila
+        assert_eq!(rays.len(), 8 * 4 + 2 * 4 * 4);
+        assert!(rays.iter().filter(|&&(x, _)| x.fract() != 0.0).all(|&(x, _)| (2.5..4.5).contains(&x)));
+        // Lejos del borde la imagen no cambia
+        assert_eq!(image[0], Radiance::new(1.0, 1.0, 1.0));
+        assert_eq!(image[7], Radiance::BLACK);
+
+        // Sin contraste no se refina nada
+        let flat = Mutex::new(0);
+        supersample(8, 4, pattern, PixelFilter::Box, 0, |_, _, _| {
+            *flat.lock().unwrap() += 1;
+            Radiance::new(0.5, 0.5, 0.5)
+        });
+        assert_eq!(flat.into_inner().unwrap(), 8 * 4);
+    }
+
+    #[test]
+    fn adaptive_center_ray_is_jittered_after_the_first_pass() {
+        let pattern = SamplePattern::Adaptive { n: 2, threshold: 10.0 };
+        let (_, first) = half_white(pattern, PixelFilter::Box, 0);
+        assert!(first.iter().all(|&(x, y)| x.fract() == 0.0 && y.fract() == 0.0));
+
+        let (_, later) = half_white(pattern, PixelFilter::Box, 1);
+        assert_eq!(later.len(), 8 * 4);
+        assert!(later.iter().all(|&(x, y)| {
+            let (dx, dy) = (x - x.round(), y - y.round());
+            (-0.5..=0.5).contains(&dx) && (-0.5..=0.5).contains(&dy)
+        }));
+        assert!(later.iter().any(|&(x, y)| x.fract() != 0.0 || y.fract() != 0.0));
+    }
+
+    #[test]
+    fn wide_filters_blend_across_the_edge() {
+        let (boxed, _) = half_white(SamplePattern::Grid { n: 2 }, PixelFilter::Box, 0);
+        let (tent, _) = half_white(SamplePattern::Grid { n: 2 }, PixelFilter::Tent, 0);
+        // Con caja cada píxel solo ve sus muestras; la tienda mezcla el borde con el vecino
+        assert_eq!((boxed[3].r, boxed[4].r), (1.0, 0.0));
+        assert!(tent[3].r < 1.0 && tent[4].r > 0.0);
+        assert_eq!(tent[0].r, 1.0);
+    }
+}
@@ -0,0 +1,392 @@
+use nalgebra_glm::Vec3;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+// Máximo de objetos por hoja del árbol
+const MAX_LEAF_SIZE: usize = 4;
+
+// Caja alineada a los ejes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    // Caja vacía, neutra para `union`
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    // Eje (0 = X, 1 = Y, 2 = Z) en el que la caja es más larga
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.max - self.min;
+        if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn contains(&self, point: &Vec3) -> bool {
+        (0..3).all(|axis| point[axis] >= self.min[axis] && point[axis] <= self.max[axis])
+    }
+
+    // Prueba de slabs; devuelve la distancia de entrada si el rayo toca la caja antes de `t_max`
+    // o justo en él, para no descartar un objeto empatado con el más cercano
+    pub fn hit(&self, ray_origin: &Vec3, inv_dir: &Vec3, t_max: f32) -> Option<f32> {
+        let t0 = (self.min - ray_origin).component_mul(inv_dir);
+        let t1 = (self.max - ray_origin).component_mul(inv_dir);
+
+        let t_enter = t0.x.min(t1.x).max(t0.y.min(t1.y)).max(t0.z.min(t1.z));
+        let t_exit = t0.x.max(t1.x).min(t0.y.max(t1.y)).min(t0.z.max(t1.z));
+
+        if t_enter <= t_exit && t_exit >= 0.0 && t_enter <= t_max {
+            Some(t_enter.max(0.0))
+        } else {
+            None
+        }
+    }
+}
+
+enum NodeKind {
+    Leaf { start: usize, count: usize },
+    Interior { left: usize, right: usize },
+}
+
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+// Jerarquía de volúmenes envolventes sobre los objetos de una escena.
+// Guarda índices a `objects`, así que hay que reconstruirla (o ajustarla con `refit`)
+// cuando la lista de objetos cambia.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn build(objects: &[Box<dyn RayIntersect>]) -> Self {
+        let bounds: Vec<Aabb> = objects.iter().map(|o| o.bounding_box()).collect();
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * objects.len()),
+            indices: (0..objects.len()).collect(),
+        };
+
+        if !objects.is_empty() {
+            bvh.build_node(&bounds, 0, objects.len());
+        }
+        bvh
+    }
+
+    // Construye el nodo para `indices[start..end]` y devuelve su posición
+    fn build_node(&mut self, bounds: &[Aabb], start: usize, end: usize) -> usize {
+        let node_bounds = self.indices[start..end]
+            .iter()
+            .fold(Aabb::empty(), |acc, &i| acc.union(&bounds[i]));
+
+        let node_index = self.nodes.len();
+        let count = end - start;
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            kind: NodeKind::Leaf { start, count },
+        });
+        if count <= MAX_LEAF_SIZE {
+            return node_index;
+        }
+
+        // Partimos por la mediana de los centroides en el eje más largo
+        let centroid_bounds = self.indices[start..end].iter().fold(Aabb::empty(), |acc, &i| {
+            let c = bounds[i].centroid();
+            acc.union(&Aabb::new(c, c))
+        });
+        let axis = centroid_bounds.longest_axis();
+        let mid = start + count / 2;
+        self.indices[start..end].select_nth_unstable_by(count / 2, |&a, &b| {
+            bounds[a].centroid()[axis].total_cmp(&bounds[b].centroid()[axis])
+        });
+
+        let left = self.build_node(bounds, start, mid);
+        let right = self.build_node(bounds, mid, end);
+        self.nodes[node_index].kind = NodeKind::Interior { left, right };
+        node_index
+    }
+
+    // Recalcula las cajas sin cambiar la topología; útil cuando los objetos solo se mueven un poco
+    pub fn refit(&mut self, objects: &[Box<dyn RayIntersect>]) {
+        // Los hijos siempre quedan después del padre, así que basta recorrer al revés
+        for node_index in (0..self.nodes.len()).rev() {
+            let bounds = match self.nodes[node_index].kind {
+                NodeKind::Leaf { start, count } => self.indices[start..start + count]
+                    .iter()
+                    .fold(Aabb::empty(), |acc, &i| acc.union(&objects[i].bounding_box())),
+                NodeKind::Interior { left, right } => {
+                    self.nodes[left].bounds.union(&self.nodes[right].bounds)
+                }
+            };
+            self.nodes[node_index].bounds = bounds;
+        }
+    }
+
+    // Intersección más cercana entre el rayo y los objetos. Si dos objetos quedan a la misma
+    // distancia gana el de menor índice, igual que en el recorrido lineal de `Scene::intersect`
+    pub fn intersect<'a>(
+        &self,
+        objects: &'a [Box<dyn RayIntersect>],
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+    ) -> Intersect<'a> {
+        let mut closest_intersect = Intersect::empty();
+        if self.nodes.is_empty() {
+            return closest_intersect;
+        }
+
+        let inv_dir = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+        let mut zbuffer = f32::INFINITY;
+        let mut closest_index = usize::MAX;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.bounds.hit(ray_origin, &inv_dir, zbuffer).is_none() {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf { start, count } => {
+                    for &i in &self.indices[start..start + count] {
+                        let intersect = objects[i].ray_intersect(ray_origin, ray_direction);
+                        let closer = intersect.distance < zbuffer
+                            || (intersect.distance == zbuffer && i < closest_index);
+                        if intersect.is_intersecting && closer {
+                            zbuffer = intersect.distance;
+                            closest_index = i;
+                            closest_intersect = intersect;
+                        }
+                    }
+                }
+                NodeKind::Interior { left, right } => {
+                    // Visitamos primero el hijo más cercano para recortar antes el resto
+                    let t_left = self.nodes[left].bounds.hit(ray_origin, &inv_dir, zbuffer);
+                    let t_right = self.nodes[right].bounds.hit(ray_origin, &inv_dir, zbuffer);
+                    match (t_left, t_right) {
+                        (Some(tl), Some(tr)) if tl <= tr => {
+                            stack.push(right);
+                            stack.push(left);
+                        }
+                        (Some(_), Some(_)) => {
+                            stack.push(left);
+                            stack.push(right);
+                        }
+                        (Some(_), None) => stack.push(left),
+                        (None, Some(_)) => stack.push(right),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+
+        closest_intersect
+    }
+
+    // ¿Algún objeto tapa el rayo antes de `max_t`? Se detiene en el primer objeto que lo tape,
+    // sin buscar el más cercano, así que no importa el orden de visita
+    pub fn occluded(&self, objects: &[Box<dyn RayIntersect>], ray_origin: &Vec3, ray_direction: &Vec3, max_t: f32) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inv_dir = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.bounds.hit(ray_origin, &inv_dir, max_t).is_none() {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf { start, count } => {
+                    if self.indices[start..start + count]
+                        .iter()
+                        .any(|&i| objects[i].occludes(ray_origin, ray_direction, max_t))
+                    {
+                        return true;
+                    }
+                }
+                NodeKind::Interior { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::ray_intersect::Material;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::sync::Arc;
+
+    // Cada cubo lleva un color distinto para saber cuál tocó el rayo
+    fn cube(index: usize, center: Vec3, size: f32) -> Box<dyn RayIntersect> {
+        let material = Arc::new(Material::new(Color::new(index as u8, (index >> 8) as u8, 1), 32.0, [1.0, 0.0, 0.0, 0.0], 1.0, false, None));
+        Box::new(Cube::new(center, size, std::array::from_fn(|_| material.clone())))
+    }
+
+    fn random_cubes(rng: &mut StdRng, count: usize) -> Vec<Box<dyn RayIntersect>> {
+        (0..count)
+            .map(|i| {
+                let center = Vec3::new(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0));
+                cube(i, center, rng.gen_range(0.5..2.0))
+            })
+            .collect()
+    }
+
+    fn random_ray(rng: &mut StdRng) -> (Vec3, Vec3) {
+        let origin = Vec3::new(rng.gen_range(-15.0..15.0), rng.gen_range(-15.0..15.0), rng.gen_range(-15.0..15.0));
+        let target = Vec3::new(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0));
+        (origin, (target - origin).normalize())
+    }
+
+    // Recorrido lineal de referencia: el primer objeto gana los empates
+    fn brute_force<'a>(objects: &'a [Box<dyn RayIntersect>], origin: &Vec3, direction: &Vec3) -> Intersect<'a> {
+        let mut closest = Intersect::empty();
+        let mut zbuffer = f32::INFINITY;
+        for object in objects {
+            let intersect = object.ray_intersect(origin, direction);
+            if intersect.is_intersecting && intersect.distance < zbuffer {
+                zbuffer = intersect.distance;
+                closest = intersect;
+            }
+        }
+        closest
+    }
+
+    fn assert_same_hit(expected: &Intersect, actual: &Intersect) {
+        assert_eq!(expected.is_intersecting, actual.is_intersecting);
+        if expected.is_intersecting {
+            assert_eq!(expected.distance, actual.distance);
+            assert_eq!(expected.material.diffuse, actual.material.diffuse);
+        }
+    }
+
+    fn assert_matches_brute_force(bvh: &Bvh, objects: &[Box<dyn RayIntersect>], rng: &mut StdRng) {
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let (origin, direction) = random_ray(rng);
+            let expected = brute_force(objects, &origin, &direction);
+            assert_same_hit(&expected, &bvh.intersect(objects, &origin, &direction));
+            hits += expected.is_intersecting as usize;
+        }
+        assert!(hits > 100, "muy pocos rayos tocaron algo: {}", hits);
+    }
+
+    #[test]
+    fn intersect_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let objects = random_cubes(&mut rng, 200);
+        let bvh = Bvh::build(&objects);
+        assert_matches_brute_force(&bvh, &objects, &mut rng);
+    }
+
+    #[test]
+    fn occluded_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let objects = random_cubes(&mut rng, 200);
+        let bvh = Bvh::build(&objects);
+
+        let (mut blocked, mut clear) = (0, 0);
+        for _ in 0..2000 {
+            let (origin, direction) = random_ray(&mut rng);
+            let max_t = rng.gen_range(0.0..30.0);
+            let expected = objects.iter().any(|object| object.occludes(&origin, &direction, max_t));
+            assert_eq!(bvh.occluded(&objects, &origin, &direction, max_t), expected, "{:?} {:?} {}", origin, direction, max_t);
+            if expected {
+                blocked += 1;
+            } else {
+                clear += 1;
+            }
+        }
+        assert!(blocked > 100 && clear > 100, "{} tapados, {} libres", blocked, clear);
+    }
+
+    #[test]
+    fn ties_go_to_the_lowest_index() {
+        // Dos cubos idénticos en los índices 3 y 40: cualquier rayo que los toque empata
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut objects = random_cubes(&mut rng, 60);
+        objects[3] = cube(3, Vec3::new(20.0, 0.0, 0.0), 1.0);
+        objects[40] = cube(40, Vec3::new(20.0, 0.0, 0.0), 1.0);
+        let bvh = Bvh::build(&objects);
+
+        for (x, y) in [(0.0, 0.0), (0.3, -0.2), (-0.4, 0.45)] {
+            let origin = Vec3::new(20.0 + x, y, -5.0);
+            let direction = Vec3::new(0.0, 0.0, 1.0);
+            let intersect = bvh.intersect(&objects, &origin, &direction);
+            assert!(intersect.is_intersecting);
+            assert_eq!(intersect.material.diffuse, Color::new(3, 0, 1));
+        }
+    }
+
+    #[test]
+    fn empty_scene_never_hits() {
+        let bvh = Bvh::build(&[]);
+        let intersect = bvh.intersect(&[], &Vec3::zeros(), &Vec3::new(0.0, 0.0, 1.0));
+        assert!(!intersect.is_intersecting);
+    }
+
+    #[test]
+    fn refit_follows_moved_objects() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut objects = random_cubes(&mut rng, 200);
+        let mut bvh = Bvh::build(&objects);
+
+        for object in objects.iter_mut() {
+            let cube = object.as_any_mut().downcast_mut::<Cube>().unwrap();
+            cube.center += Vec3::new(rng.gen_range(-3.0..3.0), rng.gen_range(-3.0..3.0), rng.gen_range(-3.0..3.0));
+        }
+        bvh.refit(&objects);
+
+        // Cada nodo debe volver a envolver exactamente a sus objetos
+        for node in &bvh.nodes {
+            if let NodeKind::Leaf { start, count } = node.kind {
+                let bounds = bvh.indices[start..start + count]
+                    .iter()
+                    .fold(Aabb::empty(), |acc, &i| acc.union(&objects[i].bounding_box()));
+                assert_eq!(node.bounds, bounds);
+            }
+        }
+        assert_matches_brute_force(&bvh, &objects, &mut rng);
+    }
+}
@@ -1,15 +1,156 @@
 use nalgebra_glm::Vec3;
-use std::f32::consts::PI;
+use std::f32::consts::{FRAC_PI_2, PI};
+use crate::raytracer::RayCone;
+use crate::sampling::concentric_disk;
 
+// Cómo se proyecta la escena en la imagen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // Perspectiva con el campo de visión vertical de la cámara
+    Perspective,
+    // Rayos paralelos (tomas isométricas del diorama); `height` es la altura visible en unidades del mundo
+    Orthographic { height: f32 },
+    // Panorama de 360° x 180° (latitud-longitud) alrededor del ojo; el centro de la imagen mira hacia `center`
+    Equirectangular,
+}
+
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub eye: Vec3,
     pub center: Vec3,
     pub up: Vec3,
+    pub fov: f32,                // Campo de visión vertical en radianes (solo en perspectiva)
+    pub near: f32,               // Los rayos empiezan a esta distancia del ojo (plano de recorte cercano)
+    pub projection: Projection,
+    pub aperture: f32,           // Radio de la lente; con 0 la cámara es estenopeica y todo sale enfocado
+    pub focus_distance: f32,     // Distancia del ojo al plano enfocado, a lo largo de la vista
 }
 
 impl Camera {
+    // Cámara en perspectiva con 90° de campo de visión vertical, sin desenfoque y enfocada en `center`
     pub fn new(eye: Vec3, center: Vec3, up: Vec3) -> Self {
-        Camera { eye, center, up }
+        Camera {
+            eye,
+            center,
+            up,
+            fov: FRAC_PI_2,
+            near: 0.0,
+            projection: Projection::Perspective,
+            aperture: 0.0,
+            focus_distance: (center - eye).magnitude(),
+        }
+    }
+
+    pub fn with_fov(mut self, fov: f32) -> Self {
+        self.fov = fov;
+        self
+    }
+
+    pub fn with_near(mut self, near: f32) -> Self {
+        self.near = near.max(0.0);
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_aperture(mut self, aperture: f32) -> Self {
+        self.aperture = aperture.max(0.0);
+        self
+    }
+
+    pub fn with_focus_distance(mut self, focus_distance: f32) -> Self {
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    // Si la lente tiene apertura, los rayos de un píxel salen de distintos puntos de ella y solo
+    // coinciden en el plano de enfoque (la proyección equirectangular no tiene lente)
+    pub fn has_depth_of_field(&self) -> bool {
+        self.aperture > 0.0 && self.projection != Projection::Equirectangular
+    }
+
+    // Enfoca el plano que pasa por `point`
+    pub fn focus_on(&mut self, point: &Vec3) {
+        let forward = (self.center - self.eye).normalize();
+        self.focus_distance = (point - self.eye).dot(&forward).max(1e-3);
+    }
+
+    // Origen y dirección unitaria del rayo que pasa por el punto (px, py) de una imagen de
+    // `width` x `height` píxeles, en píxeles: el píxel (x, y) cubre de (x - 0.5, y - 0.5) a
+    // (x + 0.5, y + 0.5), así que (x, y) es su centro. Los rayos arrancan en el plano de recorte cercano
+    pub fn generate_ray(&self, px: f32, py: f32, width: usize, height: usize) -> (Vec3, Vec3) {
+        let aspect_ratio = width as f32 / height as f32;
+        let screen_x = (2.0 * (px + 0.5)) / width as f32 - 1.0;
+        let screen_y = -((2.0 * (py + 0.5)) / height as f32 - 1.0);
+        match self.projection {
+            Projection::Perspective => {
+                let scale = self.fov_scale();
+                let direction = self.base_change(&Vec3::new(screen_x * aspect_ratio * scale, screen_y * scale, -1.0).normalize());
+                // El plano cercano es perpendicular a la vista, no una esfera alrededor del ojo
+                let forward = (self.center - self.eye).normalize();
+                (self.eye + direction * (self.near / direction.dot(&forward)), direction)
+            }
+            Projection::Orthographic { height: view_height } => {
+                let forward = (self.center - self.eye).normalize();
+                let right = forward.cross(&self.up).normalize();
+                let up = right.cross(&forward).normalize();
+                let half = view_height / 2.0;
+                let origin = self.eye + right * (screen_x * aspect_ratio * half) + up * (screen_y * half) + forward * self.near;
+                (origin, forward)
+            }
+            Projection::Equirectangular => {
+                let longitude = screen_x * PI;
+                let latitude = screen_y * FRAC_PI_2;
+                let local = Vec3::new(latitude.cos() * longitude.sin(), latitude.sin(), -latitude.cos() * longitude.cos());
+                let direction = self.base_change(&local);
+                (self.eye + direction * self.near, direction)
+            }
+        }
+    }
+
+    // Como `generate_ray`, pero con una lente delgada: el rayo sale del punto (lens_u, lens_v)
+    // de la lente, con ambos en [0, 1), y pasa por el punto del plano de enfoque que ve el
+    // rayo estenopeico. Promediando muchos puntos de la lente, lo que está fuera del plano
+    // de enfoque se desenfoca
+    pub fn generate_lens_ray(&self, px: f32, py: f32, width: usize, height: usize, lens_u: f32, lens_v: f32) -> (Vec3, Vec3) {
+        let (origin, direction) = self.generate_ray(px, py, width, height);
+        if !self.has_depth_of_field() {
+            return (origin, direction);
+        }
+        let forward = (self.center - self.eye).normalize();
+        let right = forward.cross(&self.up).normalize();
+        let up = right.cross(&forward).normalize();
+
+        let along = direction.dot(&forward);
+        let lens_center = origin - direction * (self.near / along);
+        let focus_point = lens_center + direction * (self.focus_distance / along);
+        let (dx, dy) = concentric_disk(lens_u, lens_v);
+        let lens_point = lens_center + right * (dx * self.aperture) + up * (dy * self.aperture);
+        let direction = (focus_point - lens_point).normalize();
+        (lens_point + direction * (self.near / direction.dot(&forward)), direction)
+    }
+
+    // Cono que envuelve los rayos de un píxel en una imagen de `height` píxeles de alto
+    pub fn ray_cone(&self, height: usize) -> RayCone {
+        match self.projection {
+            Projection::Perspective => RayCone { width: 0.0, spread: 2.0 * self.fov_scale() / height as f32 },
+            Projection::Orthographic { height: view_height } => RayCone { width: view_height / height as f32, spread: 0.0 },
+            Projection::Equirectangular => RayCone { width: 0.0, spread: PI / height as f32 },
+        }
+    }
+
+    // Altura que se ve a la distancia de `center` con el campo de visión actual. La proyección
+    // ortográfica la usa por defecto, así encuadra lo mismo que la perspectiva
+    pub fn framing_height(&self) -> f32 {
+        2.0 * (self.center - self.eye).magnitude() * self.fov_scale()
+    }
+
+    // Mitad de la altura de la pantalla a distancia 1
+    fn fov_scale(&self) -> f32 {
+        (self.fov / 2.0).tan()
     }
 
     pub fn base_change(&self, vector: &Vec3) -> Vec3 {
@@ -67,3 +208,125 @@ impl Camera {
         self.center -= right * distance;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).magnitude() < 1e-5
+    }
+
+    // Cámara en el origen que mira hacia -z
+    fn camera() -> Camera {
+        Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0))
+    }
+
+    #[test]
+    fn center_pixel_looks_at_the_center() {
+        // En una imagen impar el píxel central está justo en el eje de la vista
+        let (origin, direction) = camera().generate_ray(2.0, 2.0, 5, 5);
+        assert!(close(origin, Vec3::zeros()));
+        assert!(close(direction, Vec3::new(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn perspective_edges_follow_the_field_of_view() {
+        for fov in [30f32, 60.0, 90.0, 120.0] {
+            let camera = camera().with_fov(fov.to_radians());
+            // El borde superior de la imagen (y = -0.5) está a fov / 2 del centro
+            let (_, direction) = camera.generate_ray(1.5, -0.5, 4, 4);
+            let angle = direction.dot(&Vec3::new(0.0, 0.0, -1.0)).acos().to_degrees();
+            assert!((angle - fov / 2.0).abs() < 1e-3, "fov {}: {}", fov, angle);
+            assert!(direction.y > 0.0 && direction.x.abs() < 1e-6);
+        }
+        // En horizontal el ángulo crece con la proporción de la imagen
+        let (_, direction) = camera().generate_ray(7.5, 1.5, 8, 4);
+        assert!(close(direction, Vec3::new(2.0, 0.0, -1.0).normalize()));
+    }
+
+    #[test]
+    fn near_plane_moves_the_origin_along_the_view() {
+        let camera = camera().with_near(2.0);
+        let (origin, direction) = camera.generate_ray(-0.5, -0.5, 4, 4);
+        // El origen está sobre el plano z = -2, no a distancia 2 del ojo
+        assert!((origin.z + 2.0).abs() < 1e-5);
+        assert!(close(origin, direction * (2.0 / -direction.z)));
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let camera = camera().with_near(1.0).with_projection(Projection::Orthographic { height: 4.0 });
+        let (corner, corner_direction) = camera.generate_ray(-0.5, -0.5, 8, 4);
+        let (center, center_direction) = camera.generate_ray(3.5, 1.5, 8, 4);
+        assert!(close(corner_direction, Vec3::new(0.0, 0.0, -1.0)));
+        assert!(close(center_direction, Vec3::new(0.0, 0.0, -1.0)));
+        assert!(close(center, Vec3::new(0.0, 0.0, -1.0)));
+        // La altura visible es 4 y la imagen es el doble de ancha que de alta
+        assert!(close(corner, Vec3::new(-4.0, 2.0, -1.0)));
+    }
+
+    #[test]
+    fn equirectangular_covers_the_whole_sphere() {
+        let camera = camera().with_projection(Projection::Equirectangular);
+        let direction = |x: f32, y: f32| camera.generate_ray(x, y, 8, 4).1;
+        assert!(close(direction(3.5, 1.5), Vec3::new(0.0, 0.0, -1.0)));
+        // Un cuarto de la imagen hacia un lado gira 90°, y el borde queda detrás
+        assert!(close(direction(5.5, 1.5), Vec3::new(1.0, 0.0, 0.0)));
+        assert!(close(direction(1.5, 1.5), Vec3::new(-1.0, 0.0, 0.0)));
+        assert!(close(direction(7.5, 1.5), Vec3::new(0.0, 0.0, 1.0)));
+        // Los bordes de arriba y abajo son los polos
+        assert!(close(direction(3.5, -0.5), Vec3::new(0.0, 1.0, 0.0)));
+        assert!(close(direction(3.5, 3.5), Vec3::new(0.0, -1.0, 0.0)));
+    }
+
+    #[test]
+    fn ray_cone_matches_the_pixel_size() {
+        let cone = camera().with_fov(FRAC_PI_2).ray_cone(100);
+        assert!((cone.spread - 0.02).abs() < 1e-6);
+        let cone = camera().with_projection(Projection::Orthographic { height: 10.0 }).ray_cone(100);
+        assert_eq!((cone.width, cone.spread), (0.1, 0.0));
+    }
+
+    #[test]
+    fn lens_rays_meet_on_the_focus_plane() {
+        let camera = camera().with_near(0.5).with_aperture(0.25).with_focus_distance(4.0);
+        let (_, pinhole) = camera.generate_ray(1.0, 2.0, 4, 4);
+        let focus_point = pinhole * (4.0 / -pinhole.z);
+        for (u, v) in [(0.0, 0.0), (0.9, 0.1), (0.3, 0.7), (0.99, 0.99)] {
+            let (origin, direction) = camera.generate_lens_ray(1.0, 2.0, 4, 4, u, v);
+            // Arranca en el plano cercano, dentro de la lente, y cruza el plano z = -4 en el mismo punto
+            assert!((origin.z + 0.5).abs() < 1e-5);
+            let lens_point = origin - direction * (0.5 / -direction.z);
+            assert!(lens_point.z.abs() < 1e-5 && lens_point.magnitude() <= 0.25 + 1e-5);
+            assert!(close(lens_point + direction * (4.0 / -direction.z), focus_point));
+        }
+        // Las esquinas de la lente sí separan los rayos
+        let (a, _) = camera.generate_lens_ray(1.0, 2.0, 4, 4, 0.0, 0.5);
+        let (b, _) = camera.generate_lens_ray(1.0, 2.0, 4, 4, 1.0, 0.5);
+        assert!((a - b).magnitude() > 0.4);
+    }
+
+    #[test]
+    fn without_aperture_lens_rays_are_pinhole_rays() {
+        let camera = camera().with_near(0.5).with_focus_distance(4.0);
+        assert!(!camera.has_depth_of_field());
+        assert_eq!(camera.generate_lens_ray(1.0, 2.0, 4, 4, 0.9, 0.1), camera.generate_ray(1.0, 2.0, 4, 4));
+        // Los panoramas no tienen lente
+        let panorama = camera.with_aperture(1.0).with_projection(Projection::Equirectangular);
+        assert!(!panorama.has_depth_of_field());
+        assert_eq!(panorama.generate_lens_ray(1.0, 2.0, 4, 4, 0.9, 0.1), panorama.generate_ray(1.0, 2.0, 4, 4));
+    }
+
+    #[test]
+    fn focus_on_measures_along_the_view() {
+        let mut camera = camera();
+        assert_eq!(camera.focus_distance, 1.0);
+        // Un punto a un lado enfoca el plano que lo contiene, no la esfera
+        camera.focus_on(&Vec3::new(3.0, 1.0, -6.0));
+        assert!((camera.focus_distance - 6.0).abs() < 1e-6);
+        // Un punto detrás del ojo no deja la distancia en 0 ni negativa
+        camera.focus_on(&Vec3::new(0.0, 0.0, 2.0));
+        assert!(camera.focus_distance > 0.0);
+    }
+}
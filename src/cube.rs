@@ -1,18 +1,40 @@
 use nalgebra_glm::Vec3;
 use crate::ray_intersect::{Intersect, RayIntersect, Material};
-use image::RgbaImage;
-use crate::color::Color;
-use crate::light::Light; 
 use std::any::Any;
+use std::sync::Arc;
+use crate::bvh::Aabb;
+
+// Coordenadas UV de un punto sobre la cara `face_index` de la caja [min, max]
+// (0/1 = -X/+X, 2/3 = -Y/+Y, 4/5 = -Z/+Z)
+pub fn face_uv(face_index: usize, punto_encuentro: &Vec3, min: &Vec3, max: &Vec3) -> (f32, f32) {
+    match face_index {
+        // Caras derecha e izquierda
+        0 | 1 => (
+            1.0 - (punto_encuentro.z - min.z) / (max.z - min.z),
+            1.0 - (punto_encuentro.y - min.y) / (max.y - min.y),
+        ),
+        // Caras inferior y superior
+        2 | 3 => (
+            (punto_encuentro.x - min.x) / (max.x - min.x),
+            (punto_encuentro.z - min.z) / (max.z - min.z),
+        ),
+        // Caras trasera y frontal
+        4 | 5 => (
+            1.0 - (punto_encuentro.x - min.x) / (max.x - min.x),
+            1.0 - (punto_encuentro.y - min.y) / (max.y - min.y),
+        ),
+        _ => (0.0, 0.0),
+    }
+}
 
 pub struct Cube {
     pub center: Vec3,
     pub size: f32,
-    pub materials: [Material; 6],
+    pub materials: [Arc<Material>; 6],
 }
 
 impl Cube {
-    pub fn new(center: Vec3, size: f32, materials: [Material; 6]) -> Self {
+    pub fn new(center: Vec3, size: f32, materials: [Arc<Material>; 6]) -> Self {
         Cube { center, size, materials }
     }
 
@@ -21,72 +43,26 @@ impl Cube {
         let min = self.center - Vec3::new(mitad, mitad, mitad);
         let max = self.center + Vec3::new(mitad, mitad, mitad);
 
-        let mut u = 0.0;
-        let mut v = 0.0;
-
-        if (punto_encuentro.x - min.x).abs() < 0.001 {
-            // Cara derecha
-            u = 1.0 - (punto_encuentro.z - min.z) / (max.z - min.z);
-            v = 1.0 - (punto_encuentro.y - min.y) / (max.y - min.y);
+        let cara = if (punto_encuentro.x - min.x).abs() < 0.001 {
+            0
         } else if (punto_encuentro.x - max.x).abs() < 0.001 {
-            // Cara izquierda
-            u = 1.0 - (punto_encuentro.z - min.z) / (max.z - min.z);
-            v = 1.0 - (punto_encuentro.y - min.y) / (max.y - min.y);
+            1
         } else if (punto_encuentro.y - min.y).abs() < 0.001 {
-            // Cara inferior
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.z - min.z) / (max.z - min.z);
+            2
         } else if (punto_encuentro.y - max.y).abs() < 0.001 {
-            // Cara superior
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.z - min.z) / (max.z - min.z);
+            3
         } else if (punto_encuentro.z - min.z).abs() < 0.001 {
-            // Cara trasera
-            u = 1.0 - (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = 1.0 - (punto_encuentro.y - min.y) / (max.y - min.y);
+            4
         } else if (punto_encuentro.z - max.z).abs() < 0.001 {
-            // Cara frontal
-            u = 1.0 - (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = 1.0 - (punto_encuentro.y - min.y) / (max.y - min.y);
-        }
-        (u, v)
-    }
-
-    fn get_diffuse_color(&self, face_index: usize, u: f32, v: f32) -> Color {
-        if let Some(texture) = &self.materials[face_index].texture {
-            // Convertimos `u32` a `usize` para que sea compatible con `get_pixel`
-            let tex_x = (u * texture.width() as f32) as usize % texture.width();
-            let tex_y = (v * texture.height() as f32) as usize % texture.height();
-            
-            // Obtenemos el color del píxel usando `get_pixel`
-            let pixel = texture.get_pixel(tex_x, tex_y);
-
-            // Creamos un color a partir de los componentes del píxel
-            Color::new(pixel.r, pixel.g, pixel.b)
+            5
         } else {
-            self.materials[face_index].diffuse.clone()
-        }
+            return (0.0, 0.0);
+        };
+        face_uv(cara, punto_encuentro, &min, &max)
     }
-}
-
-impl RayIntersect for Cube {
-    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
-        let mitad = self.size / 2.0;
-        let min = self.center - Vec3::new(mitad, mitad, mitad);
-        let max = self.center + Vec3::new(mitad, mitad, mitad);
 
-        let inv_dir = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
-        let t_min = (min - ray_origin).component_mul(&inv_dir);
-        let t_max = (max - ray_origin).component_mul(&inv_dir);
-
-        let t1 = t_min.x.min(t_max.x).max(t_min.y.min(t_max.y)).max(t_min.z.min(t_max.z));
-        let t2 = t_min.x.max(t_max.x).min(t_min.y.max(t_max.y)).min(t_min.z.max(t_max.z));
-
-        if t1 > t2 || t2 < 0.0 {
-            return Intersect::empty();
-        }
-
-        let t_hit = if t1 < 0.0 { t2 } else { t1 };
+    // Intersección con la cara de la caja [min, max] que el rayo toca en `t_hit`
+    fn face_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, t_hit: f32, min: &Vec3, max: &Vec3) -> Intersect<'_> {
         let punto_encuentro = ray_origin + ray_direction * t_hit;
 
         let mut normal = Vec3::new(0.0, 0.0, 0.0);
@@ -116,44 +92,67 @@ impl RayIntersect for Cube {
         let u = u.clamp(0.0, 1.0);
         let v = v.clamp(0.0, 1.0);
 
-        let textura_color = self.get_diffuse_color(face_index, u, v);
-
         Intersect::new(
             punto_encuentro,
             normal,
             t_hit,
-            self.materials[face_index].clone(),
+            &self.materials[face_index],
             u,
             v
         )
+        .with_uv_scale(1.0 / self.size)
     }
+}
 
-    fn get_uv(&self, punto_encuentro: &Vec3) -> (f32, f32) {
+impl RayIntersect for Cube {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect<'_> {
         let mitad = self.size / 2.0;
         let min = self.center - Vec3::new(mitad, mitad, mitad);
         let max = self.center + Vec3::new(mitad, mitad, mitad);
 
-        let mut u = 0.0;
-        let mut v = 0.0;
+        let inv_dir = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+        let t_min = (min - ray_origin).component_mul(&inv_dir);
+        let t_max = (max - ray_origin).component_mul(&inv_dir);
 
-        if (punto_encuentro.x - min.x).abs() < 0.001 {
-            u = (punto_encuentro.z - min.z) / (max.z - min.z);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        } else if (punto_encuentro.x - max.x).abs() < 0.001 {
-            u = (punto_encuentro.z - min.z) / (max.z - min.z);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        } else if (punto_encuentro.y - min.y).abs() < 0.001 {
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.z - min.z) / (max.z - min.z);
-        } else if (punto_encuentro.y - max.y).abs() < 0.001 {
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.z - min.z) / (max.z - min.z);
-        } else if (punto_encuentro.z - min.z).abs() < 0.001 {
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
+        let t1 = t_min.x.min(t_max.x).max(t_min.y.min(t_max.y)).max(t_min.z.min(t_max.z));
+        let t2 = t_min.x.max(t_max.x).min(t_min.y.max(t_max.y)).min(t_min.z.max(t_max.z));
+
+        if t1 > t2 || t2 < 0.0 {
+            return Intersect::empty();
+        }
+
+        // Prueba alfa: si la cara de entrada es transparente en ese texel, el rayo
+        // atraviesa el bloque y puede ver la cara trasera (hojas, vidrio recortado)
+        if t1 >= 0.0 {
+            let entrada = self.face_hit(ray_origin, ray_direction, t1, &min, &max);
+            if !entrada.material.is_cutout(entrada.u, entrada.v) {
+                return entrada;
+            }
         }
 
-        (u, v)
+        let salida = self.face_hit(ray_origin, ray_direction, t2, &min, &max);
+        if salida.material.is_cutout(salida.u, salida.v) {
+            return Intersect::empty();
+        }
+        salida
+    }
+
+    // Un punto dentro del bloque (por ejemplo, el fondo bajo un bloque de agua) no queda
+    // en sombra por el propio bloque
+    fn occludes(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_t: f32) -> bool {
+        if self.bounding_box().contains(ray_origin) {
+            return false;
+        }
+        let intersect = self.ray_intersect(ray_origin, ray_direction);
+        intersect.is_intersecting && intersect.distance < max_t
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let mitad = self.size / 2.0;
+        Aabb::new(
+            self.center - Vec3::new(mitad, mitad, mitad),
+            self.center + Vec3::new(mitad, mitad, mitad),
+        )
     }
 
     fn as_any_mut(&mut self) -> &mut dyn Any {
@@ -0,0 +1,124 @@
+use nalgebra::Vector3;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::light::Light;
+use crate::radiance::Radiance;
+
+// Cielo de noche (el fondo de siempre del diorama, también el del entorno por defecto y
+// hacia el que se funde el cielo analítico al ponerse el sol) y de día (el azul de Minecraft)
+pub const NIGHT_SKY: Color = Color { r: 4, g: 12, b: 36 };
+const DAY_SKY: Color = Color { r: 90, g: 140, b: 235 };
+// Resplandor del horizonte al amanecer y al atardecer
+const TWILIGHT_GLOW: Color = Color { r: 255, g: 128, b: 64 };
+
+// Inclinación de la órbita hacia -Z, para que las sombras del mediodía no caigan rectas
+const ORBIT_TILT: f32 = 0.35;
+// Radio angular del sol y de la luna (un poco mayores que los reales, como los cuadrados de Minecraft)
+const SUN_ANGULAR_RADIUS: f32 = 0.02;
+const MOON_ANGULAR_RADIUS: f32 = 0.015;
+
+// Hora del día para el sol y la luna. Como en Minecraft, el sol sale por +X (este)
+// a las 6:00, pasa por arriba a las 12:00 y se pone por -X a las 18:00; la luna va en
+// el lado opuesto de la órbita
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Daylight {
+    pub time: f32,           // Hora en [0, 24)
+    pub sun_intensity: f32,  // Intensidad del sol al mediodía
+    pub moon_intensity: f32, // Intensidad de la luna a medianoche
+}
+
+impl Daylight {
+    pub fn new(time: f32) -> Self {
+        Daylight {
+            time: time.rem_euclid(24.0),
+            sun_intensity: 2.0,
+            moon_intensity: 0.25,
+        }
+    }
+
+    // Adelantar (o atrasar, con horas negativas) el reloj, dando la vuelta a las 24:00
+    pub fn advance(&mut self, hours: f32) {
+        self.time = (self.time + hours).rem_euclid(24.0);
+    }
+
+    // Dirección unitaria hacia el sol
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let angle = (self.time - 6.0) / 24.0 * 2.0 * PI;
+        Vector3::new(angle.cos(), angle.sin() * ORBIT_TILT.cos(), -angle.sin() * ORBIT_TILT.sin())
+    }
+
+    // Seno de la altura del sol sobre el horizonte: 1 al mediodía, negativo de noche
+    pub fn sun_elevation(&self) -> f32 {
+        self.sun_direction().y
+    }
+
+    // Cuánto es de día, en [0, 1]; pasa de 0 a 1 mientras el sol cruza el horizonte
+    pub fn day_factor(&self) -> f32 {
+        smoothstep(-0.1, 0.15, self.sun_elevation())
+    }
+
+    // El sol: rojizo cerca del horizonte y blanco arriba. Sin intensidad cuando está bajo el horizonte
+    pub fn sun(&self) -> Light {
+        let elevation = self.sun_elevation();
+        let temperature = 1900.0 + 3900.0 * smoothstep(0.0, 0.5, elevation);
+        Light::directional(
+            self.sun_direction(),
+            kelvin_to_color(temperature),
+            self.sun_intensity * smoothstep(-0.05, 0.1, elevation),
+            SUN_ANGULAR_RADIUS,
+        )
+    }
+
+    // La luna, fría y tenue, en el lado opuesto del cielo
+    pub fn moon(&self) -> Light {
+        Light::directional(
+            -self.sun_direction(),
+            kelvin_to_color(8000.0),
+            self.moon_intensity * smoothstep(-0.05, 0.1, -self.sun_elevation()),
+            MOON_ANGULAR_RADIUS,
+        )
+    }
+
+    // El sol y la luna que están sobre el horizonte
+    pub fn lights(&self) -> Vec<Light> {
+        [self.sun(), self.moon()]
+            .into_iter()
+            .filter(|light| light.intensity > 0.0)
+            .collect()
+    }
+
+    // Color del cielo: de la noche al azul del día, con un resplandor cálido en el crepúsculo
+    pub fn sky_color(&self) -> Radiance {
+        let day = self.day_factor();
+        let sky = Radiance::from_srgb(NIGHT_SKY).scale(1.0 - day) + Radiance::from_srgb(DAY_SKY).scale(day);
+        let twilight = (-(self.sun_elevation() / 0.15).powi(2)).exp() * 0.6;
+        sky.scale(1.0 - twilight) + Radiance::from_srgb(TWILIGHT_GLOW).scale(twilight)
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Color de un cuerpo negro a `kelvin` grados (aproximación de Tanner Helland, 1000 K a 40000 K)
+pub fn kelvin_to_color(kelvin: f32) -> Color {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let channel = |c: f32| c.clamp(0.0, 255.0) as u8;
+
+    let r = if t <= 66.0 { 255.0 } else { 329.699 * (t - 60.0).powf(-0.133_204_76) };
+    let g = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_16 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+
+    Color::new(channel(r), channel(g), channel(b))
+}
@@ -0,0 +1,150 @@
+use nalgebra::Vector3;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::daylight::NIGHT_SKY;
+use crate::radiance::Radiance;
+use crate::texture::{Texture, TextureFilter};
+
+// Escala de la luminancia de Preetham (en kcd/m²) a la radiancia del raytracer
+const SKY_EXPOSURE: f32 = 0.035;
+// Fracción de la luz del horizonte que devuelve el suelo bajo el cielo analítico
+const GROUND_ALBEDO: f32 = 0.3;
+
+// Lo que ven los rayos que escapan de la escena: los de cámara y también los reflejados
+// y refractados, así que el entorno aparece en el agua y el vidrio
+#[derive(Clone)]
+pub enum Environment {
+    // Un solo color (el fondo de siempre del diorama)
+    Solid(Radiance),
+    // Degradado del horizonte al cénit, y hacia `ground` bajo el horizonte
+    Gradient {
+        zenith: Radiance,
+        horizon: Radiance,
+        ground: Radiance,
+    },
+    // Cielo diurno analítico de Preetham, Shirley y Smits (1999) según la posición del sol;
+    // `turbidity` va de 2 (cielo limpio) a 10 (brumoso)
+    Sky { turbidity: f32, intensity: f32 },
+    // Mapa equirectangular (latitud-longitud) PNG, HDR o EXR; `rotation` gira el mapa
+    // alrededor de Y, en radianes
+    Map {
+        texture: Arc<Texture>,
+        intensity: f32,
+        rotation: f32,
+    },
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::Solid(Radiance::from_srgb(NIGHT_SKY))
+    }
+}
+
+impl Environment {
+    // Radiancia que llega desde la dirección unitaria `direction`. `sun` es la dirección hacia
+    // el sol, si la escena tiene uno; solo la usa el cielo analítico
+    pub fn radiance(&self, direction: &Vector3<f32>, sun: Option<Vector3<f32>>) -> Radiance {
+        match self {
+            Environment::Solid(color) => *color,
+            Environment::Gradient { zenith, horizon, ground } => {
+                if direction.y >= 0.0 {
+                    let t = direction.y.sqrt();
+                    horizon.scale(1.0 - t) + zenith.scale(t)
+                } else {
+                    let t = (-direction.y * 4.0).min(1.0);
+                    horizon.scale(1.0 - t) + ground.scale(t)
+                }
+            }
+            Environment::Sky { turbidity, intensity } => {
+                // Sin sol se usa uno de media mañana
+                let sun = sun.unwrap_or_else(|| Vector3::new(0.5, 0.6, -0.6).normalize());
+                preetham(direction, &sun, *turbidity).scale(*intensity)
+            }
+            Environment::Map { texture, intensity, rotation } => {
+                let (sin, cos) = rotation.sin_cos();
+                let x = direction.x * cos - direction.z * sin;
+                let z = direction.x * sin + direction.z * cos;
+                let u = 0.5 + x.atan2(-z) / (2.0 * PI);
+                let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+                texture.sample(u, v, TextureFilter::Bilinear, 0.0).scale(*intensity)
+            }
+        }
+    }
+}
+
+// Función de distribución de Perez: cómo cambia el cielo con el ángulo al cénit (`theta`)
+// y al sol (`gamma`)
+fn perez(theta: f32, gamma: f32, [a, b, c, d, e]: [f32; 5]) -> f32 {
+    (1.0 + a * (b / theta.cos().max(0.01)).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+fn preetham(direction: &Vector3<f32>, sun: &Vector3<f32>, turbidity: f32) -> Radiance {
+    let t = turbidity;
+    // El modelo solo vale con el sol sobre el horizonte; de noche se funde con el cielo nocturno
+    let day = ((sun.y + 0.1) / 0.25).clamp(0.0, 1.0);
+    let night = Radiance::from_srgb(NIGHT_SKY);
+    if day == 0.0 {
+        return night;
+    }
+    let sun = Vector3::new(sun.x, sun.y.max(0.02), sun.z).normalize();
+    let theta_s = sun.y.acos();
+
+    // Bajo el horizonte se ve el suelo, que devuelve parte de la luz del horizonte
+    let (view, ground) = if direction.y < 0.0 {
+        (Vector3::new(direction.x, 0.0, direction.z).try_normalize(1e-6).unwrap_or(Vector3::x()), true)
+    } else {
+        (*direction, false)
+    };
+    let theta = view.y.clamp(0.0, 1.0).acos();
+    let gamma = view.dot(&sun).clamp(-1.0, 1.0).acos();
+
+    let coeffs_y = [
+        0.1787 * t - 1.4630,
+        -0.3554 * t + 0.4275,
+        -0.0227 * t + 5.3251,
+        0.1206 * t - 2.5771,
+        -0.0670 * t + 0.3703,
+    ];
+    let coeffs_x = [
+        -0.0193 * t - 0.2592,
+        -0.0665 * t + 0.0008,
+        -0.0004 * t + 0.2125,
+        -0.0641 * t - 0.8989,
+        -0.0033 * t + 0.0452,
+    ];
+    let coeffs_yc = [
+        -0.0167 * t - 0.2608,
+        -0.0950 * t + 0.0092,
+        -0.0079 * t + 0.2102,
+        -0.0441 * t - 1.6537,
+        -0.0109 * t + 0.0529,
+    ];
+
+    // Valores en el cénit
+    let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+    let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+    let (s1, s2, s3) = (theta_s, theta_s * theta_s, theta_s * theta_s * theta_s);
+    let zenith_x = t * t * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s1)
+        + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s1 + 0.00394)
+        + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s1 + 0.25886);
+    let zenith_yc = t * t * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s1)
+        + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s1 + 0.00516)
+        + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s1 + 0.26688);
+
+    let relative = |coeffs| perez(theta, gamma, coeffs) / perez(0.0, theta_s, coeffs);
+    let luminance = (zenith_y * relative(coeffs_y)).max(0.0) * SKY_EXPOSURE;
+    let x = zenith_x * relative(coeffs_x);
+    let y = (zenith_yc * relative(coeffs_yc)).max(1e-4);
+
+    // Yxy -> XYZ -> sRGB lineal
+    let cie_x = x / y * luminance;
+    let cie_z = (1.0 - x - y) / y * luminance;
+    let sky = Radiance::new(
+        (3.2406 * cie_x - 1.5372 * luminance - 0.4986 * cie_z).max(0.0),
+        (-0.9689 * cie_x + 1.8758 * luminance + 0.0415 * cie_z).max(0.0),
+        (0.0557 * cie_x - 0.2040 * luminance + 1.0570 * cie_z).max(0.0),
+    );
+    let sky = if ground { sky.scale(GROUND_ALBEDO) } else { sky };
+
+    night.scale(1.0 - day) + sky.scale(day)
+}
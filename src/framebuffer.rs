@@ -1,7 +1,15 @@
+use image::{DynamicImage, ImageResult, Rgb32FImage, RgbImage};
+use std::path::Path;
+use crate::color::Color;
+use crate::radiance::Radiance;
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
-    pub buffer: Vec<u32>,
+    pub buffer: Vec<u32>,          // Píxeles sRGB ya con tone mapping (0xRRGGBB)
+    pub radiance: Vec<Radiance>,   // Radiancia lineal HDR de cada píxel, antes del tone mapping
+    pub accumulation: Vec<Radiance>, // Suma de las muestras acumuladas de cada píxel
+    pub samples: u32,              // Pasadas sumadas en `accumulation`
 }
 
 impl Framebuffer {
@@ -11,80 +19,57 @@ impl Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
+            radiance: vec![Radiance::BLACK; width * height],
+            accumulation: vec![Radiance::BLACK; width * height],
+            samples: 0,
         }
     }
 
-    // Método para hacer upscale bilineal
-    pub fn upscale_bilineal(&self, new_width: usize, new_height: usize) -> Framebuffer {
-        let mut high_res_buffer = vec![0; new_width * new_height];
-
-        for y in 0..new_height {
-            let src_y = y as f32 * (self.height as f32 / new_height as f32);
-            let src_y1 = src_y.floor() as usize;
-            let src_y2 = (src_y1 + 1).min(self.height - 1);
-            let weight_y = src_y - src_y1 as f32;
-
-            for x in 0..new_width {
-                let src_x = x as f32 * (self.width as f32 / new_width as f32);
-                let src_x1 = src_x.floor() as usize;
-                let src_x2 = (src_x1 + 1).min(self.width - 1);
-                let weight_x = src_x - src_x1 as f32;
-
-                let c11 = self.buffer[src_y1 * self.width + src_x1];
-                let c12 = self.buffer[src_y1 * self.width + src_x2];
-                let c21 = self.buffer[src_y2 * self.width + src_x1];
-                let c22 = self.buffer[src_y2 * self.width + src_x2];
-
-                let color = Self::bilinear_interpolation(c11, c12, c21, c22, weight_x, weight_y);
-                high_res_buffer[y * new_width + x] = color;
-            }
-        }
-
-        Framebuffer {
-            width: new_width,
-            height: new_height,
-            buffer: high_res_buffer,
-        }
+    // Descartar las pasadas acumuladas (la cámara o la escena cambiaron); el próximo render
+    // que acumula empieza de cero
+    pub fn reset_accumulation(&mut self) {
+        self.samples = 0;
     }
 
-    // Interpolación bilineal para los colores
-    fn bilinear_interpolation(c11: u32, c12: u32, c21: u32, c22: u32, weight_x: f32, weight_y: f32) -> u32 {
-        let r11 = ((c11 >> 16) & 0xFF) as f32;
-        let g11 = ((c11 >> 8) & 0xFF) as f32;
-        let b11 = (c11 & 0xFF) as f32;
-
-        let r12 = ((c12 >> 16) & 0xFF) as f32;
-        let g12 = ((c12 >> 8) & 0xFF) as f32;
-        let b12 = (c12 & 0xFF) as f32;
-
-        let r21 = ((c21 >> 16) & 0xFF) as f32;
-        let g21 = ((c21 >> 8) & 0xFF) as f32;
-        let b21 = (c21 & 0xFF) as f32;
-
-        let r22 = ((c22 >> 16) & 0xFF) as f32;
-        let g22 = ((c22 >> 8) & 0xFF) as f32;
-        let b22 = (c22 & 0xFF) as f32;
-
-        let r = (r11 * (1.0 - weight_x) * (1.0 - weight_y)
-            + r12 * weight_x * (1.0 - weight_y)
-            + r21 * (1.0 - weight_x) * weight_y
-            + r22 * weight_x * weight_y) as u32;
+    // Obtener el buffer
+    pub fn get_buffer(&self) -> &[u32] {
+        &self.buffer
+    }
 
-        let g = (g11 * (1.0 - weight_x) * (1.0 - weight_y)
-            + g12 * weight_x * (1.0 - weight_y)
-            + g21 * (1.0 - weight_x) * weight_y
-            + g22 * weight_x * weight_y) as u32;
+    // Obtener el color de un píxel en la posición (x, y)
+    pub fn get_pixel(&self, x: usize, y: usize) -> Color {
+        let pixel = self.buffer[y * self.width + x];
+        Color::new((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+    }
 
-        let b = (b11 * (1.0 - weight_x) * (1.0 - weight_y)
-            + b12 * weight_x * (1.0 - weight_y)
-            + b21 * (1.0 - weight_x) * weight_y
-            + b22 * weight_x * weight_y) as u32;
+    // Convertir el buffer a una imagen RGB de 8 bits
+    pub fn to_rgb_image(&self) -> RgbImage {
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let pixel = self.buffer[y as usize * self.width + x as usize];
+            image::Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+        })
+    }
 
-        (r << 16) | (g << 8) | b
+    // Convertir la radiancia lineal a una imagen de punto flotante
+    pub fn to_rgb32f_image(&self) -> Rgb32FImage {
+        Rgb32FImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let radiance = self.radiance[y as usize * self.width + x as usize];
+            image::Rgb([radiance.r, radiance.g, radiance.b])
+        })
     }
 
-    // Obtener el buffer
-    pub fn get_buffer(&self) -> &[u32] {
-        &self.buffer
+    // Guardar el framebuffer en disco; el formato se deduce de la extensión (png, exr, ...).
+    // OpenEXR guarda la radiancia lineal sin tone mapping; el resto, los píxeles sRGB.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        let path = path.as_ref();
+        let is_exr = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("exr"));
+
+        if is_exr {
+            DynamicImage::ImageRgb32F(self.to_rgb32f_image()).save(path)
+        } else {
+            DynamicImage::ImageRgb8(self.to_rgb_image()).save(path)
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,51 @@
+//! Raytracer del diorama de Minecraft.
+//!
+//! La biblioteca expone la construcción de escenas (`Scene`, `Cube`, `Material`, `Light`, `Camera`,
+//! o archivos JSON mediante `load_scene`),
+//! la función `render` y el `Framebuffer` resultante. El visor interactivo (`main.rs`) es solo
+//! una capa delgada encima de esta API.
+
+pub mod antialiasing;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod cube;
+pub mod daylight;
+pub mod environment;
+pub mod framebuffer;
+pub mod light;
+pub mod nbt;
+pub mod path_tracer;
+pub mod ray_intersect;
+pub mod radiance;
+pub mod raytracer;
+pub mod resource_pack;
+pub mod sampling;
+pub mod scene;
+pub mod scene_file;
+pub mod structure;
+pub mod texture;
+pub mod tonemap;
+pub mod voxel;
+
+pub use antialiasing::{PixelFilter, SamplePattern};
+pub use bvh::{Aabb, Bvh};
+pub use camera::{Camera, Projection};
+pub use color::Color;
+pub use cube::Cube;
+pub use daylight::Daylight;
+pub use environment::Environment;
+pub use framebuffer::Framebuffer;
+pub use light::{Attenuation, Light, LightKind};
+pub use ray_intersect::{Intersect, Material, RayIntersect};
+pub use radiance::Radiance;
+pub use path_tracer::trace_path;
+pub use raytracer::{cast_ray, cast_ray_cone, render, render_with_options, Integrator, RayCone, RenderOptions};
+pub use resource_pack::{BlockFace, ResourcePack, ResourcePackError};
+pub use sampling::Sampler;
+pub use scene::Scene;
+pub use scene_file::{load_scene, load_scene_with_resource_pack, SceneError};
+pub use structure::{BlockState, ImportError, Structure};
+pub use texture::{ColorSpace, Texture, TextureError, TextureFilter, TextureRegion};
+pub use tonemap::{ToneMapper, ToneMapping};
+pub use voxel::VoxelGrid;
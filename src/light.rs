@@ -1,12 +1,39 @@
 use nalgebra::Vector3;
 use crate::color::Color;
+use crate::sampling::{concentric_disk, orthonormal_basis};
+
+// Tipo de luz
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Point,                                   // Esfera en `position`
+    Directional { direction: Vector3<f32> }, // Muy lejana (sol, luna): `direction` apunta hacia la luz
+    // Foco en `position` que alumbra hacia `direction`: con toda la intensidad dentro de
+    // `inner_angle` y bajando hasta 0 en `outer_angle` (semiángulos en radianes)
+    Spot {
+        direction: Vector3<f32>,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+// Cómo baja la intensidad de las luces puntuales y focos con la distancia
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Attenuation {
+    #[default]
+    None,                  // Intensidad constante (las luces lejanas del diorama)
+    InverseSquare,         // Física: intensidad / distancia²
+    Linear { range: f32 }, // Como la luz de bloques de Minecraft: baja en línea recta hasta 0 en `range`
+}
 
 #[derive(Clone)]
 pub struct Light {
-    pub position: Vector3<f32>,  // Posición de la luz en el espacio 3D
+    pub position: Vector3<f32>,  // Posición de la luz en el espacio 3D (no se usa en las direccionales)
     pub color: Color,            // Color de la luz
     pub intensity: f32,          // Intensidad de la luz
-    pub radius: f32,             // Radio de la luz
+    pub radius: f32,             // Radio de la esfera que emite (en las direccionales, radio angular en radianes);
+                                 // 0 es una luz puntual (sombras duras)
+    pub kind: LightKind,
+    pub attenuation: Attenuation, // No se aplica a las direccionales
 }
 
 impl Light {
@@ -16,6 +43,114 @@ impl Light {
             color,
             intensity,
             radius,
+            kind: LightKind::Point,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    // Foco en `position` apuntando hacia `direction`
+    pub fn spot(
+        position: Vector3<f32>,
+        direction: Vector3<f32>,
+        color: Color,
+        intensity: f32,
+        radius: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        Light {
+            kind: LightKind::Spot {
+                direction: direction.normalize(),
+                inner_angle: inner_angle.min(outer_angle),
+                outer_angle,
+            },
+            ..Light::new(position, color, intensity, radius)
+        }
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    // Luz direccional que llega desde `direction` con el mismo ángulo a toda la escena
+    pub fn directional(direction: Vector3<f32>, color: Color, intensity: f32, angular_radius: f32) -> Self {
+        Light {
+            position: Vector3::zeros(),
+            color,
+            intensity,
+            radius: angular_radius,
+            kind: LightKind::Directional { direction: direction.normalize() },
+            attenuation: Attenuation::None,
+        }
+    }
+
+    // Intensidad que llega a `point` sin contar sombras: la atenuación por distancia y,
+    // en los focos, el cono
+    pub fn intensity_at(&self, point: &Vector3<f32>) -> f32 {
+        let (cone_direction, inner_angle, outer_angle) = match self.kind {
+            LightKind::Directional { .. } => return self.intensity,
+            LightKind::Point => (None, 0.0, 0.0),
+            LightKind::Spot { direction, inner_angle, outer_angle } => (Some(direction), inner_angle, outer_angle),
+        };
+
+        let to_point = point - self.position;
+        let distance = to_point.magnitude();
+        let falloff = match self.attenuation {
+            Attenuation::None => 1.0,
+            Attenuation::InverseSquare => 1.0 / (distance * distance).max(1e-4),
+            Attenuation::Linear { range } => (1.0 - distance / range).max(0.0),
+        };
+        let cone = match cone_direction {
+            Some(direction) if distance > 0.0 => {
+                let cos = (to_point / distance).dot(&direction);
+                let (cos_outer, cos_inner) = (outer_angle.cos(), inner_angle.cos());
+                if cos_inner - cos_outer > 1e-6 {
+                    ((cos - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0)
+                } else if cos >= cos_outer {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            _ => 1.0,
+        };
+        self.intensity * falloff * cone
+    }
+
+    // Dirección unitaria desde `from` hacia el centro de la luz y distancia hasta ella
+    // (infinita en las direccionales)
+    pub fn direction_from(&self, from: &Vector3<f32>) -> (Vector3<f32>, f32) {
+        match self.kind {
+            LightKind::Point | LightKind::Spot { .. } => {
+                let to_light = self.position - from;
+                let distance = to_light.magnitude();
+                (to_light / distance, distance)
+            }
+            LightKind::Directional { direction } => (direction, f32::INFINITY),
+        }
+    }
+
+    // Dirección hacia un punto de la luz para el par (u, v) del cuadrado unitario, y distancia.
+    // Desde fuera, la esfera se ve como un disco perpendicular a la dirección hacia ella,
+    // así que se muestrea ese disco; en las direccionales, el disco del cielo que cubre la luz
+    pub fn sample_direction(&self, from: &Vector3<f32>, u: f32, v: f32) -> (Vector3<f32>, f32) {
+        let (center, distance) = self.direction_from(from);
+        if self.radius <= 0.0 || distance <= self.radius {
+            return (center, distance);
+        }
+        let (tangent, bitangent) = orthonormal_basis(&center);
+        let (x, y) = concentric_disk(u, v);
+        match self.kind {
+            LightKind::Point | LightKind::Spot { .. } => {
+                let to_point = self.position + (tangent * x + bitangent * y) * self.radius - from;
+                let distance = to_point.magnitude();
+                (to_point / distance, distance)
+            }
+            LightKind::Directional { .. } => {
+                let spread = self.radius.tan();
+                ((center + (tangent * x + bitangent * y) * spread).normalize(), f32::INFINITY)
+            }
         }
     }
 }
@@ -1,833 +1,481 @@
-mod framebuffer;
-mod raytracer;
-mod ray_intersect;
-mod camera;
-mod light;
-mod color;
-mod texture;
-mod cube;
-
-use framebuffer::Framebuffer;
-use nalgebra::Vector3;
-use ray_intersect::Material;
-use camera::Camera;
-use light::Light;
-use color::Color;
-use std::f32::consts::PI;
-use std::time::Duration;
-use std::io::{self, Write};
-use minifb::{Key, Window, WindowOptions};
-use std::path::Path;
-use std::time::Instant;
-use crate::raytracer::render;
-use crate::texture::Texture;
-use crate::ray_intersect::RayIntersect;
-use crate::cube::Cube;
+use minecraft_diorama::{
+    load_scene, load_scene_with_resource_pack, render_with_options, Cube, Daylight, Framebuffer, Integrator, PixelFilter,
+    Projection, RenderOptions, SamplePattern, Scene, ToneMapper, ToneMapping,
+};
+use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
+use std::process;
+use std::time::{Duration, Instant};
 
+const USAGE: &str = "Uso: MinecraftDioramaRaytracing [opciones]
+  --scene <escena.json>                 Escena a cargar (assets/diorama.json)
+  --resource-pack <carpeta>             Tomar las texturas de los bloques de un resource pack
+  --output <archivo.png|archivo.exr>    Renderizar un cuadro sin ventana y guardarlo
+  --width <px> --height <px>            Resolución (800x600)
+  --threads <n> --tile-size <filas>     Hilos de rayon y filas por bloque
+  --tonemap <clamp|reinhard|aces>       Operador de tone mapping (aces)
+  --exposure <factor>                   Exposición lineal (1.0)
+  --shadow-samples <n>                  Rayos de sombra por luz para las sombras suaves (16)
+  --time <horas>                        Hora del día (0-24): ilumina con el sol y la luna
+  --day-length <segundos>               En el visor, duración de un día completo (ciclo animado)
+  --fov <grados>                        Campo de visión vertical (el de la escena, 90 por defecto)
+  --projection <perspective|orthographic|equirectangular>
+                                        Proyección de la cámara; equirectangular es un panorama de 360°
+  --aperture <radio>                    Radio de la lente para la profundidad de campo (el de la escena, 0)
+  --focus-distance <d>                  Distancia al plano enfocado (la distancia al centro de la cámara)
+  --integrator <whitted|path>           Whitted (por defecto) o path tracing con iluminación global
+  --max-bounces <n>                     Rebotes del path tracer (5)
+  --passes <n>                          Muestras por píxel acumuladas con la cámara quieta (64; sin ventana,
+                                        1 con Whitted); 1 desactiva la acumulación en el visor
+  --aa <none|grid|stratified|adaptive>  Supersampling: rayos por píxel en cada pasada (none)
+  --aa-samples <n>                      Lado de la cuadrícula de rayos por píxel (3, es decir 3x3)
+  --aa-threshold <t>                    Contraste entre vecinos que refina un píxel con adaptive (0.25)
+  --aa-filter <box|tent|mitchell>       Filtro de reconstrucción de las muestras (box)
+
+Controles del visor: WASD mueve la cámara, flechas la giran, T/G adelantan/atrasan la hora,
+clic izquierdo enfoca el bloque bajo el cursor";
+
+// Opciones de línea de comandos
+struct CliOptions {
+    scene: String,
+    resource_pack: Option<String>,
+    output: Option<String>,
+    width: usize,
+    height: usize,
+    threads: Option<usize>,
+    tile_size: usize,
+    tone_mapper: ToneMapper,
+    shadow_samples: u32,
+    time: Option<f32>,
+    day_length: Option<f32>,
+    fov: Option<f32>,
+    projection: Option<String>,
+    aperture: Option<f32>,
+    focus_distance: Option<f32>,
+    integrator: Integrator,
+    passes: Option<u32>,
+    sampling: SamplePattern,
+    filter: PixelFilter,
+}
+
+// Muestras por píxel acumuladas si no se indica otra cosa
+const DEFAULT_PASSES: u32 = 64;
+
+impl CliOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = CliOptions {
+            scene: "assets/diorama.json".to_string(),
+            resource_pack: None,
+            output: None,
+            width: 800,
+            height: 600,
+            threads: None,
+            tile_size: RenderOptions::default().tile_size,
+            tone_mapper: ToneMapper::default(),
+            shadow_samples: RenderOptions::default().shadow_samples,
+            time: None,
+            day_length: None,
+            fov: None,
+            projection: None,
+            aperture: None,
+            focus_distance: None,
+            integrator: Integrator::Whitted,
+            passes: None,
+            sampling: SamplePattern::Center,
+            filter: PixelFilter::Box,
+        };
+        let mut max_bounces = None;
+        let mut aa_samples = None;
+        let mut aa_threshold = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--scene" | "-s" => {
+                    options.scene = Self::value(arg, iter.next())?.to_string();
+                }
+                "--resource-pack" => {
+                    options.resource_pack = Some(Self::value(arg, iter.next())?.to_string());
+                }
+                "--output" | "-o" => {
+                    options.output = Some(Self::value(arg, iter.next())?.to_string());
+                }
+                "--width" => options.width = Self::dimension(arg, iter.next())?,
+                "--height" => options.height = Self::dimension(arg, iter.next())?,
+                "--threads" => options.threads = Some(Self::dimension(arg, iter.next())?),
+                "--tile-size" => options.tile_size = Self::dimension(arg, iter.next())?,
+                "--tonemap" => {
+                    options.tone_mapper.operator = Self::value(arg, iter.next())?.parse::<ToneMapping>()?;
+                }
+                "--exposure" => options.tone_mapper.exposure = Self::factor(arg, iter.next())?,
+                "--shadow-samples" => options.shadow_samples = Self::count(arg, iter.next())?,
+                "--time" => options.time = Some(Self::factor(arg, iter.next())?),
+                "--day-length" => match Self::factor(arg, iter.next())? {
+                    seconds if seconds > 0.0 => options.day_length = Some(seconds),
+                    _ => return Err(format!("Valor inválido para {}: debe ser mayor que 0", arg)),
+                },
+                "--fov" => match Self::factor(arg, iter.next())? {
+                    degrees if degrees > 0.0 && degrees < 180.0 => options.fov = Some(degrees),
+                    _ => return Err(format!("Valor inválido para {}: debe estar entre 0 y 180 grados", arg)),
+                },
+                "--projection" => match Self::value(arg, iter.next())?.to_ascii_lowercase().as_str() {
+                    projection @ ("perspective" | "orthographic" | "equirectangular") => {
+                        options.projection = Some(projection.to_string())
+                    }
+                    other => {
+                        return Err(format!(
+                            "proyección desconocida: {} (perspective, orthographic, equirectangular)",
+                            other
+                        ))
+                    }
+                },
+                "--aperture" => options.aperture = Some(Self::factor(arg, iter.next())?),
+                "--focus-distance" => match Self::factor(arg, iter.next())? {
+                    distance if distance > 0.0 => options.focus_distance = Some(distance),
+                    _ => return Err(format!("Valor inválido para {}: debe ser positiva", arg)),
+                },
+                "--integrator" => options.integrator = Self::value(arg, iter.next())?.parse::<Integrator>()?,
+                "--max-bounces" => max_bounces = Some(Self::count(arg, iter.next())?),
+                "--passes" => options.passes = Some(Self::count(arg, iter.next())?),
+                "--aa" => options.sampling = Self::value(arg, iter.next())?.parse::<SamplePattern>()?,
+                "--aa-samples" => aa_samples = Some(Self::count(arg, iter.next())?),
+                "--aa-threshold" => aa_threshold = Some(Self::factor(arg, iter.next())?),
+                "--aa-filter" => options.filter = Self::value(arg, iter.next())?.parse::<PixelFilter>()?,
+                "--help" | "-h" => return Err(USAGE.to_string()),
+                _ => return Err(format!("Argumento desconocido: {}\n{}", arg, USAGE)),
+            }
+        }
+
+        match (&mut options.integrator, max_bounces) {
+            (Integrator::PathTracing { max_bounces }, Some(bounces)) => *max_bounces = bounces,
+            (Integrator::Whitted, Some(_)) => return Err("--max-bounces solo se usa con --integrator path".to_string()),
+            (_, None) => {}
+        }
+        if let Some(n) = aa_samples {
+            if options.sampling == SamplePattern::Center {
+                return Err("--aa-samples solo se usa con --aa grid, stratified o adaptive".to_string());
+            }
+            options.sampling = options.sampling.with_samples_per_side(n);
+        }
+        match (&mut options.sampling, aa_threshold) {
+            (SamplePattern::Adaptive { threshold, .. }, Some(t)) => *threshold = t,
+            (_, Some(_)) => return Err("--aa-threshold solo se usa con --aa adaptive".to_string()),
+            (_, None) => {}
+        }
+
+        Ok(options)
+    }
+
+    fn value<'a>(flag: &str, value: Option<&'a String>) -> Result<&'a str, String> {
+        value
+            .map(|v| v.as_str())
+            .ok_or_else(|| format!("Falta el valor para {}", flag))
+    }
+
+    fn factor(flag: &str, value: Option<&String>) -> Result<f32, String> {
+        let value = Self::value(flag, value)?;
+        match value.parse::<f32>() {
+            Ok(f) if f.is_finite() && f >= 0.0 => Ok(f),
+            _ => Err(format!("Valor inválido para {}: {}", flag, value)),
+        }
+    }
+
+    fn dimension(flag: &str, value: Option<&String>) -> Result<usize, String> {
+        let value = Self::value(flag, value)?;
+        match value.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Valor inválido para {}: {}", flag, value)),
+        }
+    }
+
+    // Cantidad de rayos o pasadas; los valores que no caben en un u32 también son inválidos
+    fn count(flag: &str, value: Option<&String>) -> Result<u32, String> {
+        let value = Self::value(flag, value)?;
+        match value.parse::<u32>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Valor inválido para {}: {}", flag, value)),
+        }
+    }
+}
 
 fn main() {
-    let width = 800;
-    let height = 600;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match CliOptions::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    };
 
+    let loaded = match &options.resource_pack {
+        Some(pack) => load_scene_with_resource_pack(&options.scene, pack),
+        None => load_scene(&options.scene),
+    };
+    let mut scene = match loaded {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Error al cargar la escena: {}", e);
+            process::exit(1);
+        }
+    };
+    for warning in &scene.warnings {
+        eprintln!("Aviso: {}", warning);
+    }
+    if let Some(time) = options.time {
+        scene.daylight = Some(Daylight::new(time));
+    }
+    if let Some(fov) = options.fov {
+        scene.camera.fov = fov.to_radians();
+    }
+    match options.projection.as_deref() {
+        Some("perspective") => scene.camera.projection = Projection::Perspective,
+        // Encuadra lo mismo que la perspectiva a la distancia del centro
+        Some("orthographic") => scene.camera.projection = Projection::Orthographic { height: scene.camera.framing_height() },
+        Some("equirectangular") => scene.camera.projection = Projection::Equirectangular,
+        _ => {}
+    }
+    if let Some(aperture) = options.aperture {
+        scene.camera.aperture = aperture;
+    }
+    if let Some(distance) = options.focus_distance {
+        scene.camera.focus_distance = distance;
+    }
+
+    let mut render_options = RenderOptions::default()
+        .with_tile_size(options.tile_size)
+        .with_tone_mapper(options.tone_mapper)
+        .with_shadow_samples(options.shadow_samples)
+        .with_integrator(options.integrator)
+        .with_sampling(options.sampling)
+        .with_filter(options.filter);
+    if let Some(threads) = options.threads {
+        render_options = match render_options.with_threads(threads) {
+            Ok(render_options) => render_options,
+            Err(e) => {
+                eprintln!("No se pudo crear el pool de hilos: {}", e);
+                process::exit(1);
+            }
+        };
+    }
+
+    match options.output {
+        Some(ref output) => {
+            process::exit(render_offline(scene, &render_options, output, options.width, options.height, options.passes))
+        }
+        None => {
+            let passes = options.passes.unwrap_or(DEFAULT_PASSES);
+            process::exit(run_viewer(scene, &render_options, options.width, options.height, options.day_length, passes))
+        }
+    }
+}
+
+// Renderiza un solo cuadro sin abrir ventana y lo guarda en disco, acumulando `passes`
+// pasadas (por defecto, solo con el path tracer)
+fn render_offline(scene: Scene, render_options: &RenderOptions, output: &str, width: usize, height: usize, passes: Option<u32>) -> i32 {
+    let mut framebuffer = Framebuffer::new(width, height);
+    let passes = match (passes, render_options.integrator) {
+        (Some(passes), _) => passes,
+        // Con profundidad de campo, un solo punto de la lente por píxel queda lleno de ruido
+        (None, Integrator::Whitted) if scene.camera.has_depth_of_field() => DEFAULT_PASSES,
+        (None, Integrator::Whitted) => 1,
+        (None, Integrator::PathTracing { .. }) => DEFAULT_PASSES,
+    };
+    let render_options = render_options.clone().with_accumulation(passes > 1);
+
+    let start = Instant::now();
+    for _ in 0..passes {
+        render_with_options(&mut framebuffer, &scene, &render_options);
+    }
+    println!("Render de {}x{} completado en {:.2?}", width, height, start.elapsed());
+
+    match framebuffer.save(output) {
+        Ok(()) => {
+            println!("Imagen guardada en {}", output);
+            0
+        }
+        Err(e) => {
+            eprintln!("No se pudo guardar {}: {}", output, e);
+            1
+        }
+    }
+}
+
+// Abre la ventana interactiva; devuelve el código de salida del programa. Con la cámara quieta
+// cada cuadro suma una pasada hasta llegar a `passes`; después solo se atienden los eventos
+fn run_viewer(
+    mut scene: Scene,
+    render_options: &RenderOptions,
+    width: usize,
+    height: usize,
+    day_length: Option<f32>,
+    passes: u32,
+) -> i32 {
     // Initialize framebuffers
     let mut framebuffer_high = Framebuffer::new(width, height);
-    let mut framebuffer_low = Framebuffer::new(width / 3, height / 3);
+    // Al menos un píxel, aunque la ventana mida menos de 3 de lado
+    let mut framebuffer_low = Framebuffer::new((width / 3).max(1), (height / 3).max(1));
+    // Mientras la cámara se mueve basta una sombra dura por luz y un rayo por píxel, sin path tracing
+    let preview_options = render_options
+        .clone()
+        .with_shadow_samples(1)
+        .with_integrator(Integrator::Whitted)
+        .with_sampling(SamplePattern::Center)
+        .with_depth_of_field(false);
+    // Con `--passes 1` se vuelve a renderizar cada cuadro desde cero, con el agua animada
+    let progressive = passes > 1;
+    let still_options = render_options.clone().with_accumulation(progressive);
 
-    // Inicializamos la ventana con minifb
-    let mut window = Window::new(
+    // Inicializamos la ventana con minifb; sin pantalla (SSH, CI) se sugiere el render sin ventana
+    let mut window = match Window::new(
         "Minecraft Diorama Raytracing",
         width,
         height,
         WindowOptions::default(),
-    )
-    .unwrap_or_else(|e| {
-        panic!("{}", e);
-    });
-
-    // Definimos las texturas a utilizar
-    let agua_texture = Texture::load_from_file("assets/agua.jpg");
-    let tierra_texture = Texture::load_from_file("assets/tierra.jpeg");
-    let tierra_grama_texture = Texture::load_from_file("assets/tierra2.png");
-    let grama_texture = Texture::load_from_file("assets/grama.png");
-    let madera_texture = Texture::load_from_file("assets/madera.jpg");
-    let hoja_texture = Texture::load_from_file("assets/hoja_arbol.jpg");
-    let piedra_texture = Texture::load_from_file("assets/piedra.png");
-    let arena_texture = Texture::load_from_file("assets/arena.png");
-
-    //let textura_solida = Color::new(255, 0, 0);
-    //let material_prueba = Material::new(textura_solida, 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, false, None);
-
-    // Definimos la cámara
-    let mut camera = Camera::new(
-        Vector3::new(0.0, 5.0, -10.0),  // Posición de la cámara
-        Vector3::new(0.0, 0.0, 0.0),  // Punto que la cámara está mirando (centro de la escena)
-        Vector3::new(0.0, 1.0, 0.0),  // Vector "up"
-    );
-
-    // Definimos la luz
-    let lights = vec![
-        Light::new(Vector3::new(100.0, 100.0, -100.0), Color::new(255, 255, 255), 2.0, 5.0), 
-        Light::new(Vector3::new(-100.0, -100.0, 100.0), Color::new(255, 255, 255), 2.0, 5.0),
-    ];
-
-    // Definimos los materiales 
-    let tierra_grama = Material::new(Color::new(255, 255, 255), 32.0, [0.9, 0.1, 0.0, 0.0], 1.0, true, Some(tierra_grama_texture.clone()));
-    let tierra = Material::new(Color::new(255, 255, 255), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, true, Some(tierra_texture.clone()));
-    let grama = Material::new(Color::new(255, 255, 255), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, true, Some(grama_texture.clone()));
-    let agua = Material::new(Color::new(255, 255, 255), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, true, Some(agua_texture.clone()));
-    let madera = Material::new(Color::new(255, 255, 255), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, true, Some(madera_texture.clone()));
-    let piedra = Material::new(Color::new(255, 255, 255), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, true, Some(piedra_texture.clone()));
-    let hoja = Material::new(Color::new(255, 255, 255), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, true, Some(hoja_texture.clone()));
-    let arena = Material::new(Color::new(255, 255, 255), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, true, Some(arena_texture.clone()));
-
-    // Creamos los cubos en la escena
-    let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
-
-    // Base de 8x8 cubos
-    let grid_size = 8;
-    let cube_size = 1.0;
-    
-    // Recorrer en ambos ejes X y Z para crear una cuadrícula
-    for z in 0..grid_size {
-        for x in 0..grid_size {
-            let x_pos = x as f32 * cube_size - (grid_size as f32 / 2.0) * cube_size;
-            let z_pos = z as f32 * cube_size - (grid_size as f32 / 2.0) * cube_size;
-            
-            objects.push(Box::new(Cube {
-                center: Vector3::new(x_pos, 0.0, z_pos),  // Posición del cubo
-                size: cube_size,                         // Tamaño del cubo
-                materials: [
-                    tierra.clone(),  // Derecha
-                    tierra.clone(),  // Izquierda
-                    tierra.clone(),  // Abajo
-                    tierra.clone(),  // Arriba
-                    tierra.clone(),  // Frente
-                    tierra.clone(),  // Atrás
-                ],
-            }));
+    ) {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("No se pudo abrir la ventana: {}", e);
+            eprintln!("Para renderizar sin ventana usa --output <archivo.png>");
+            return 1;
         }
-    }
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 1.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 1.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 1.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 1.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 1.0, 0.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 1.0, 0.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 1.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(0.0, 1.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(0.0, 1.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-1.0, 1.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-1.0, 1.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 1.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 1.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 1.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 1.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 1.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 1.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 1.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 1.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 1.0, 0.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 1.0, 0.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 1.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-1.0, 1.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-1.0, 1.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(0.0, 1.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(0.0, 1.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 1.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 1.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(), arena.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 1.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 1.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 1.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(0.0, 1.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(0.0, 1.0, 0.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-1.0, 1.0, 0.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-1.0, 1.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 1.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 1.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 1.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 1.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 1.0, 0.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 1.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 1.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 1.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 1.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 1.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 1.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(0.0, 1.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-1.0, 1.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 1.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 1.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 1.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 1.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(0.0, 1.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            tierra.clone(), tierra.clone(), tierra.clone(), grama.clone(), tierra.clone(), tierra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 2.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 2.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 2.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 2.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 2.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 2.0, -4.0),  
-        size: 1.0,                          
-        materials: [
-            tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-1.0, 1.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 1.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 1.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 1.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 1.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 1.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 1.0, 0.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 1.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 1.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-2.0, 2.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 2.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 2.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 2.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 2.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 2.0, 1.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-3.0, 3.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 3.0, 3.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(-4.0, 3.0, 2.0),  
-        size: 1.0,                          
-        materials: [
-            piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 2.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 3.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 6.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 5.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 5.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 5.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 5.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 4.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(2.0, 4.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 4.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 4.0, -2.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 4.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 4.0, -1.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(1.0, 4.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-    objects.push(Box::new(Cube {
-        center: Vector3::new(3.0, 4.0, -3.0),  
-        size: 1.0,                          
-        materials: [
-            hoja.clone(), hoja.clone(), hoja.clone(), hoja.clone(),hoja.clone(), hoja.clone(),
-        ],
-    }));
-
-    // Ejemplo de un material transparente (por ejemplo, vidrio)
-    let glass = Material::new(Color::new(255, 255, 255), 125.0, [0.0, 0.5, 0.1, 0.8], 1.5, false, None); // Vidrio, 80% transparente, índice de refracción 1.5
-    
-    let mut needs_render = true;
-    let mut camera_moved = false;
+    };
+
     let animation_start = Instant::now();
+    let mut last_frame = Instant::now();
+    // Con el ciclo animado y sin hora inicial, el día empieza al amanecer
+    if day_length.is_some() && scene.daylight.is_none() {
+        scene.daylight = Some(Daylight::new(6.0));
+    }
+    let mut mouse_was_down = false;
     
     // Bucle principal para manejar la entrada del teclado y actualizar la cámara
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        camera_moved = false;
+        let mut camera_moved = false;
 
         // Handle camera movement
         if window.is_key_down(Key::W) {
-            camera.mover_enfrente(0.2);
+            scene.camera.mover_enfrente(0.2);
             camera_moved = true;
         }
         if window.is_key_down(Key::S) {
-            camera.mover_atras(0.2);
+            scene.camera.mover_atras(0.2);
             camera_moved = true;
         }
         if window.is_key_down(Key::A) {
-            camera.mover_izq(0.2);
+            scene.camera.mover_izq(0.2);
             camera_moved = true;
         }
         if window.is_key_down(Key::D) {
-            camera.mover_der(0.2);
+            scene.camera.mover_der(0.2);
             camera_moved = true;
         }
 
         // Handle camera rotation
         if window.is_key_down(Key::Up) {
-            camera.orbit(0.0, -0.05);
+            scene.camera.orbit(0.0, -0.05);
             camera_moved = true;
         }
         if window.is_key_down(Key::Down) {
-            camera.orbit(0.0, 0.05);
+            scene.camera.orbit(0.0, 0.05);
             camera_moved = true;
         }
         if window.is_key_down(Key::Left) {
-            camera.orbit(-0.05, 0.0);
+            scene.camera.orbit(-0.05, 0.0);
             camera_moved = true;
         }
         if window.is_key_down(Key::Right) {
-            camera.orbit(0.05, 0.0);
+            scene.camera.orbit(0.05, 0.0);
             camera_moved = true;
         }
 
+        // Hora del día: T adelanta y G atrasa (activan el ciclo si la escena no lo tenía);
+        // con --day-length avanza sola
+        let frame_time = last_frame.elapsed().as_secs_f32();
+        last_frame = Instant::now();
+        let mut hours = 0.0;
+        if window.is_key_down(Key::T) {
+            hours += 0.1;
+        }
+        if window.is_key_down(Key::G) {
+            hours -= 0.1;
+        }
+        if let Some(seconds) = day_length {
+            hours += 24.0 * frame_time / seconds;
+        }
+        if hours != 0.0 {
+            let daylight = scene.daylight.get_or_insert_with(|| Daylight::new(12.0));
+            daylight.advance(hours);
+            let minutes = (daylight.time * 60.0) as u32;
+            window.set_title(&format!("Minecraft Diorama Raytracing - {:02}:{:02}", minutes / 60, minutes % 60));
+        }
+
+        // Clic izquierdo: enfocar el primer bloque bajo el cursor
+        let mut refocused = false;
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if mouse_down && !mouse_was_down {
+            if let Some((mouse_x, mouse_y)) = window.get_mouse_pos(MouseMode::Discard) {
+                // La posición del ratón va de 0 a `width`; `generate_ray` espera el centro del píxel en x
+                let (origin, direction) = scene.camera.generate_ray(mouse_x - 0.5, mouse_y - 0.5, width, height);
+                let hit = scene.intersect(&origin, &direction);
+                if hit.is_intersecting {
+                    scene.camera.focus_on(&hit.point);
+                    refocused = scene.camera.has_depth_of_field();
+                }
+            }
+        }
+        mouse_was_down = mouse_down;
+
+        if camera_moved || refocused || hours != 0.0 || !progressive {
+            framebuffer_high.reset_accumulation();
+        }
+
         // Rendering
-        if camera_moved {
+        let updated = if camera_moved {
             // Render at low resolution during movement
-            render(&mut framebuffer_low, &objects, &camera, &lights);
+            render_with_options(&mut framebuffer_low, &scene, &preview_options);
             let scaled_framebuffer = upscale_framebuffer(framebuffer_low.get_buffer(), framebuffer_low.width, framebuffer_low.height, framebuffer_high.width, framebuffer_high.height);
-            window.update_with_buffer(&scaled_framebuffer, framebuffer_high.width, framebuffer_high.height).unwrap();
+            window.update_with_buffer(&scaled_framebuffer, framebuffer_high.width, framebuffer_high.height)
+        } else if framebuffer_high.samples < passes {
+            // Con la cámara quieta, una pasada más a resolución completa
+            render_with_options(&mut framebuffer_high, &scene, &still_options);
+            window.update_with_buffer(framebuffer_high.get_buffer(), framebuffer_high.width, framebuffer_high.height)
         } else {
-            // Render at high resolution when stationary
-            render(&mut framebuffer_high, &objects, &camera, &lights);
-            window.update_with_buffer(framebuffer_high.get_buffer(), framebuffer_high.width, framebuffer_high.height).unwrap();
-        }    
+            // La imagen ya convergió: no hay nada nuevo que dibujar
+            window.update();
+            Ok(())
+        };
+        if let Err(e) = updated {
+            eprintln!("No se pudo actualizar la ventana: {}", e);
+            return 1;
+        }
 
         // Obtener el tiempo transcurrido para la animación
         let elapsed_time = animation_start.elapsed().as_secs_f32();
 
-        // Animar los cubos de agua
-        for (i, object) in objects.iter_mut().enumerate() {
-            if let Some(cube) = object.as_any_mut().downcast_mut::<Cube>() {
-                // Compara si el cubo tiene textura y si esa textura es la de agua
-                if cube.materials.iter().any(|m| m.has_texture && m.texture.as_ref().map_or(false, |t| t == &agua_texture)) {
-                    let desfase = i as f32 * 0.2;
-                    cube.center.x += (elapsed_time * 1.0 + desfase).sin() * 0.1;
+        // Animar los cubos de agua; se congelan mientras se acumula, si no la imagen nunca
+        // convergería
+        let animated: &[usize] = if progressive && !camera_moved { &[] } else { &scene.animated };
+        let mut water_moved = false;
+        for &i in animated {
+            if let Some(cube) = scene.objects[i].as_any_mut().downcast_mut::<Cube>() {
+                let desfase = i as f32 * 0.2;
+                let delta = (elapsed_time * 1.0 + desfase).sin() * 0.1;
+                if delta != 0.0 {
+                    cube.center.x += delta;
+                    water_moved = true;
                 }
             }
         }
+        // Solo hace falta reajustar las cajas de la BVH si algún bloque se movió
+        if water_moved {
+            scene.refit_bvh();
+        }
 
         // Añadimos un pequeño delay para que no consuma tanto CPU
         std::thread::sleep(Duration::from_millis(16));
     }
+
+    0
 }
 
 fn upscale_framebuffer(low_res_buffer: &[u32], low_width: usize, low_height: usize, high_width: usize, high_height: usize) -> Vec<u32> {
@@ -0,0 +1,378 @@
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+// Profundidad máxima de anidamiento, la misma que acepta Minecraft
+const MAX_DEPTH: usize = 512;
+
+// Tope de bytes descomprimidos, para que un archivo pequeño no se infle hasta agotar la memoria
+const MAX_DECOMPRESSED: u64 = 256 * 1024 * 1024;
+
+// Valor de un tag NBT (el formato binario de los mundos y estructuras de Minecraft)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    // Campo de un compound
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(fields) => fields.get(key),
+            _ => None,
+        }
+    }
+
+    // Cualquier tag entero como i64
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(v) => Some(v as i64),
+            Tag::Short(v) => Some(v as i64),
+            Tag::Int(v) => Some(v as i64),
+            Tag::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&HashMap<String, Tag>> {
+        match self {
+            Tag::Compound(fields) => Some(fields),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            Tag::ByteArray(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    // Las listas de enteros también sirven (p. ej. `pos` en las estructuras)
+    pub fn as_int_array(&self) -> Option<Vec<i32>> {
+        match self {
+            Tag::IntArray(values) => Some(values.clone()),
+            Tag::List(items) => items.iter().map(|t| t.as_i64().map(|v| v as i32)).collect(),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+// Errores al leer NBT
+#[derive(Debug)]
+pub enum NbtError {
+    Decompress(std::io::Error),
+    TooLarge,
+    UnexpectedEnd,
+    InvalidTag(u8),
+    InvalidLength(i32),
+    TooDeep,
+    NotACompound,
+}
+
+impl fmt::Display for NbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NbtError::Decompress(err) => write!(f, "no se pudo descomprimir: {}", err),
+            NbtError::TooLarge => write!(f, "descomprimido ocupa más de {} MiB", MAX_DECOMPRESSED >> 20),
+            NbtError::UnexpectedEnd => write!(f, "el archivo termina antes de tiempo"),
+            NbtError::InvalidTag(id) => write!(f, "tipo de tag desconocido {}", id),
+            NbtError::InvalidLength(len) => write!(f, "longitud inválida {}", len),
+            NbtError::TooDeep => write!(f, "anidamiento de más de {} niveles", MAX_DEPTH),
+            NbtError::NotACompound => write!(f, "la raíz no es un compound"),
+        }
+    }
+}
+
+impl std::error::Error for NbtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NbtError::Decompress(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// Descomprimir como mucho `limit` bytes; leemos uno más para saber si el archivo se pasa
+fn decompress<R: Read>(decoder: R, limit: u64) -> Result<Vec<u8>, NbtError> {
+    let mut raw = Vec::new();
+    decoder.take(limit + 1).read_to_end(&mut raw).map_err(NbtError::Decompress)?;
+    if raw.len() as u64 > limit {
+        return Err(NbtError::TooLarge);
+    }
+    Ok(raw)
+}
+
+// Leer un archivo NBT completo; se detecta si viene comprimido con gzip o zlib.
+// Devuelve el compound raíz (su nombre casi siempre es vacío y se descarta)
+pub fn from_bytes(bytes: &[u8]) -> Result<Tag, NbtError> {
+    let raw;
+    let data = match bytes {
+        [0x1f, 0x8b, ..] => {
+            raw = decompress(GzDecoder::new(bytes), MAX_DECOMPRESSED)?;
+            &raw[..]
+        }
+        [0x78, ..] => {
+            raw = decompress(ZlibDecoder::new(bytes), MAX_DECOMPRESSED)?;
+            &raw[..]
+        }
+        _ => bytes,
+    };
+
+    let mut reader = Reader { data, pos: 0 };
+    match reader.u8()? {
+        10 => {
+            reader.string()?;
+            reader.payload(10, 0)
+        }
+        _ => Err(NbtError::NotACompound),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NbtError> {
+        let end = self.pos.checked_add(len).ok_or(NbtError::UnexpectedEnd)?;
+        let bytes = self.data.get(self.pos..end).ok_or(NbtError::UnexpectedEnd)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], NbtError> {
+        let mut out = [0; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, NbtError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, NbtError> {
+        Ok(i16::from_be_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, NbtError> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, NbtError> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    // Longitud de un arreglo o lista; se valida contra lo que queda del archivo
+    // para no reservar memoria de más con datos corruptos
+    fn len(&mut self, element_size: usize) -> Result<usize, NbtError> {
+        let len = self.i32()?;
+        if len < 0 || (len as usize).saturating_mul(element_size) > self.data.len() - self.pos {
+            return Err(NbtError::InvalidLength(len));
+        }
+        Ok(len as usize)
+    }
+
+    // Los textos usan "UTF-8 modificado" de Java; para nombres de bloques basta con UTF-8
+    fn string(&mut self) -> Result<String, NbtError> {
+        let len = u16::from_be_bytes(self.array()?) as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> Result<Tag, NbtError> {
+        if depth > MAX_DEPTH {
+            return Err(NbtError::TooDeep);
+        }
+        Ok(match id {
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => Tag::Float(f32::from_bits(self.i32()? as u32)),
+            6 => Tag::Double(f64::from_bits(self.i64()? as u64)),
+            7 => {
+                let len = self.len(1)?;
+                Tag::ByteArray(self.take(len)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let element = self.u8()?;
+                // Una lista de TAG_End solo puede estar vacía
+                let len = if element == 0 {
+                    match self.i32()? {
+                        0 => 0,
+                        len => return Err(NbtError::InvalidLength(len)),
+                    }
+                } else {
+                    self.len(1)?
+                };
+                let mut items = Vec::with_capacity(len.min(self.data.len() - self.pos));
+                for _ in 0..len {
+                    items.push(self.payload(element, depth + 1)?);
+                }
+                Tag::List(items)
+            }
+            10 => {
+                let mut fields = HashMap::new();
+                loop {
+                    let field = self.u8()?;
+                    if field == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    fields.insert(name, self.payload(field, depth + 1)?);
+                }
+                Tag::Compound(fields)
+            }
+            11 => {
+                let len = self.len(4)?;
+                Tag::IntArray((0..len).map(|_| self.i32()).collect::<Result<_, _>>()?)
+            }
+            12 => {
+                let len = self.len(8)?;
+                Tag::LongArray((0..len).map(|_| self.i64()).collect::<Result<_, _>>()?)
+            }
+            other => return Err(NbtError::InvalidTag(other)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    // Compound raíz sin nombre con los campos ya codificados
+    fn root(fields: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![10, 0, 0];
+        bytes.extend_from_slice(fields);
+        bytes.push(0);
+        bytes
+    }
+
+    // Tag con nombre: tipo, nombre y payload
+    fn named(id: u8, name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![id];
+        bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    #[test]
+    fn reads_scalars_strings_and_arrays() {
+        let mut fields = named(1, "b", &[0xff]);
+        fields.extend(named(3, "i", &1234i32.to_be_bytes()));
+        fields.extend(named(8, "s", &[0, 5, b's', b't', b'o', b'n', b'e']));
+        let mut ints = 2i32.to_be_bytes().to_vec();
+        ints.extend(7i32.to_be_bytes());
+        ints.extend((-1i32).to_be_bytes());
+        fields.extend(named(11, "a", &ints));
+        let tag = from_bytes(&root(&fields)).unwrap();
+
+        assert_eq!(tag.get("b"), Some(&Tag::Byte(-1)));
+        assert_eq!(tag.get("i").and_then(Tag::as_i64), Some(1234));
+        assert_eq!(tag.get("s").and_then(Tag::as_str), Some("stone"));
+        assert_eq!(tag.get("a").and_then(Tag::as_int_array), Some(vec![7, -1]));
+    }
+
+    #[test]
+    fn reads_gzip_compressed_files() {
+        let raw = root(&named(3, "DataVersion", &3465i32.to_be_bytes()));
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let tag = from_bytes(&encoder.finish().unwrap()).unwrap();
+        assert_eq!(tag.get("DataVersion").and_then(Tag::as_i64), Some(3465));
+    }
+
+    #[test]
+    fn decompression_stops_at_the_limit() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[0; 4096]).unwrap();
+        let bytes = encoder.finish().unwrap();
+        assert_eq!(decompress(GzDecoder::new(&bytes[..]), 4096).unwrap().len(), 4096);
+        assert!(matches!(decompress(GzDecoder::new(&bytes[..]), 4095), Err(NbtError::TooLarge)));
+    }
+
+    #[test]
+    fn empty_list_of_end_tags_is_accepted() {
+        let mut payload = vec![0];
+        payload.extend(0i32.to_be_bytes());
+        let tag = from_bytes(&root(&named(9, "l", &payload))).unwrap();
+        assert_eq!(tag.get("l").and_then(Tag::as_list), Some(&[][..]));
+    }
+
+    #[test]
+    fn list_of_end_tags_with_elements_is_rejected() {
+        let mut payload = vec![0];
+        payload.extend(i32::MAX.to_be_bytes());
+        let result = from_bytes(&root(&named(9, "l", &payload)));
+        assert!(matches!(result, Err(NbtError::InvalidLength(i32::MAX))));
+    }
+
+    #[test]
+    fn lengths_past_the_end_are_rejected() {
+        let mut payload = vec![3];
+        payload.extend(1_000_000i32.to_be_bytes());
+        assert!(matches!(from_bytes(&root(&named(9, "l", &payload))), Err(NbtError::InvalidLength(_))));
+        let payload = 1_000_000i32.to_be_bytes();
+        assert!(matches!(from_bytes(&root(&named(12, "a", &payload))), Err(NbtError::InvalidLength(_))));
+        assert!(matches!(from_bytes(&root(&named(3, "i", &(-1i32).to_be_bytes()[..2]))), Err(NbtError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn truncated_and_invalid_input_is_rejected() {
+        let full = root(&named(8, "s", &[0, 5, b's', b't', b'o', b'n', b'e']));
+        for end in 0..full.len() {
+            assert!(from_bytes(&full[..end]).is_err(), "se aceptó un archivo cortado en {}", end);
+        }
+        assert!(matches!(from_bytes(&[8, 0, 0]), Err(NbtError::NotACompound)));
+        assert!(matches!(from_bytes(&root(&named(13, "x", &[]))), Err(NbtError::InvalidTag(13))));
+        assert!(matches!(from_bytes(&[0x1f, 0x8b, 1, 2, 3]), Err(NbtError::Decompress(_))));
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let mut bytes = vec![10, 0, 0];
+        for _ in 0..=MAX_DEPTH + 1 {
+            bytes.extend(named(10, "", &[]));
+        }
+        assert!(matches!(from_bytes(&bytes), Err(NbtError::TooDeep)));
+    }
+}
@@ -0,0 +1,191 @@
+use nalgebra::Vector3;
+use crate::light::Light;
+use crate::radiance::Radiance;
+use crate::ray_intersect::Intersect;
+use crate::raytracer::{reflect, refract, RayCone};
+use crate::sampling::{cosine_hemisphere, Sampler};
+use crate::scene::Scene;
+
+// Rebotes que se hacen siempre; después, la ruleta rusa corta los caminos que aportan poco
+const ROULETTE_START: u32 = 3;
+// Probabilidad máxima de seguir, para que ningún camino rebote para siempre entre espejos
+const MAX_SURVIVAL: f32 = 0.95;
+
+// Una muestra de Monte Carlo de la radiancia que llega por el rayo. En cada choque se elige
+// un solo lóbulo del material (espejo, refracción o difuso) con probabilidad igual a su peso
+// en `albedo`; en los difusos se suma la luz directa de todas las luces (next-event
+// estimation) y el camino sigue en una dirección con densidad proporcional al coseno, así
+// que la luz rebota de un bloque a otro (el pasto tiñe de verde la tierra de al lado)
+pub fn trace_path(
+    ray_origin: &Vector3<f32>,
+    ray_direction: &Vector3<f32>,
+    scene: &Scene,
+    lights: &[Light],
+    sampler: &mut Sampler,
+    max_bounces: u32,
+    cone: RayCone,
+) -> Radiance {
+    let mut origin = *ray_origin;
+    let mut direction = ray_direction.normalize();
+    let mut cone = cone;
+    let mut throughput = Radiance::new(1.0, 1.0, 1.0);
+    let mut radiance = Radiance::BLACK;
+    // Si el rebote anterior fue difuso, la luz de los bloques emisivos que también son luces
+    // ya se sumó con la luz directa de ese choque
+    let mut after_diffuse = false;
+
+    for bounce in 0..=max_bounces {
+        let hit = scene.intersect(&origin, &direction);
+        if !hit.is_intersecting {
+            radiance += throughput * scene.background(&direction);
+            break;
+        }
+
+        let material = hit.material;
+        let cos_incidence = direction.dot(&hit.normal).abs().max(0.05);
+        let footprint = cone.width_at(hit.distance) / cos_incidence * hit.uv_scale;
+        cone = cone.propagate(hit.distance);
+
+        // Alfa parcial (vidrio de colores): parte de los caminos sigue de largo
+        let alpha = material.get_alpha(hit.u, hit.v);
+        if alpha < 1.0 && sampler.next_f32() >= alpha {
+            origin = hit.point + direction * 1e-3;
+            continue;
+        }
+
+        if !(after_diffuse && material.block_light) {
+            radiance += throughput * material.emitted(hit.u, hit.v, footprint);
+        }
+
+        let reflectivity = material.albedo[2];
+        let transparency = material.albedo[3];
+        let lobe = sampler.next_f32();
+        after_diffuse = lobe >= reflectivity + transparency;
+        if lobe < reflectivity {
+            direction = reflect(&direction, &hit.normal).normalize();
+            origin = hit.point + hit.normal * 1e-3;
+        } else if lobe < reflectivity + transparency {
+            direction = refract(&direction, &hit.normal, material.refractive_index).normalize();
+            origin = hit.point - hit.normal * 1e-3;
+        } else {
+            // La cara que ve el rayo (desde dentro del agua, la de abajo)
+            let normal = if hit.normal.dot(&direction) > 0.0 { -hit.normal } else { hit.normal };
+            let diffuse_color = material.sample_diffuse(hit.u, hit.v, footprint);
+            radiance += throughput * direct_light(&hit, &normal, &diffuse_color, &-direction, lights, scene, sampler);
+
+            throughput = throughput * diffuse_color.scale(material.albedo[0]);
+            direction = cosine_hemisphere(&normal, sampler.next_f32(), sampler.next_f32());
+            origin = hit.point + normal * 1e-3;
+        }
+
+        if bounce >= ROULETTE_START {
+            let survival = throughput.r.max(throughput.g).max(throughput.b).min(MAX_SURVIVAL);
+            if sampler.next_f32() >= survival {
+                break;
+            }
+            throughput = throughput.scale(1.0 / survival);
+        }
+    }
+
+    radiance
+}
+
+// Luz directa de todas las luces con un rayo de sombra por luz hacia un punto al azar de
+// su esfera; las sombras suaves salen del promedio de las pasadas. Usa el mismo difuso y
+// especular que el trazador de Whitted, así las dos imágenes tienen el mismo brillo
+fn direct_light(
+    intersect: &Intersect,
+    normal: &Vector3<f32>,
+    diffuse_color: &Radiance,
+    view_dir: &Vector3<f32>,
+    lights: &[Light],
+    scene: &Scene,
+    sampler: &mut Sampler,
+) -> Radiance {
+    let shadow_ray_origin = intersect.point + normal * 1e-2;
+    let mut sum = Radiance::BLACK;
+    for light in lights {
+        let incoming = light.intensity_at(&intersect.point);
+        if incoming <= 0.0 {
+            continue;
+        }
+        let (light_dir, distance) = light.sample_direction(&shadow_ray_origin, sampler.next_f32(), sampler.next_f32());
+        let diffuse_intensity = light_dir.dot(normal).clamp(0.0, 1.0);
+        let reflect_dir = reflect(&-light_dir, normal);
+        let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(intersect.material.specular);
+        if diffuse_intensity <= 0.0 && specular_intensity <= 0.0 {
+            continue;
+        }
+        if scene.occluded(&shadow_ray_origin, &light_dir, distance - light.radius) {
+            continue;
+        }
+
+        let light_color = Radiance::from_srgb(light.color);
+        let diffuse = (*diffuse_color * light_color).scale(intersect.material.albedo[0] * diffuse_intensity * incoming);
+        let specular = light_color.scale(intersect.material.albedo[1] * specular_intensity * incoming);
+        sum += diffuse + specular;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::ray_intersect::Material;
+    use nalgebra_glm::Vec3;
+    use std::sync::Arc;
+
+    // Caja cerrada de 3x3x3 cubos sin el del centro, toda del mismo material blanco que
+    // emite 1 y refleja en difuso la fracción `albedo`
+    fn closed_box(albedo: f32, block_light: bool) -> Scene {
+        let mut material = Material::new(Color::new(255, 255, 255), 10.0, [albedo, 0.0, 0.0, 0.0], 1.0, false, None)
+            .with_emission(Color::new(255, 255, 255), 1.0);
+        if block_light {
+            material = material.with_block_light();
+        }
+        let material = Arc::new(material);
+        let camera = Camera::new(Vec3::zeros(), Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(Vec::new(), Vec::new(), camera);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    if (x, y, z) != (0, 0, 0) {
+                        let center = Vec3::new(x as f32, y as f32, z as f32);
+                        scene.add_object(Cube::new(center, 1.0, std::array::from_fn(|_| material.clone())));
+                    }
+                }
+            }
+        }
+        scene.build_bvh();
+        scene
+    }
+
+    fn trace(scene: &Scene, seed: u64) -> Radiance {
+        let direction = Vector3::new(0.3, -0.2, 1.0);
+        trace_path(&Vector3::zeros(), &direction, scene, &[], &mut Sampler::new(seed), 3, RayCone::POINT)
+    }
+
+    #[test]
+    fn closed_box_adds_the_emission_of_every_bounce() {
+        // Cada choque suma la emisión y cada rebote difuso la multiplica por el albedo:
+        // E (1 + a + a² + a³) sin importar hacia dónde vaya el camino
+        let scene = closed_box(0.5, false);
+        for seed in 0..8 {
+            let color = trace(&scene, seed);
+            assert!((color.r - 1.875).abs() < 1e-4, "{:?}", color);
+            assert!((color.g - color.r).abs() < 1e-6 && (color.b - color.r).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn block_lights_are_not_counted_again_after_a_diffuse_bounce() {
+        // La emisión de los bloques que ya son luces solo se ve directamente
+        let scene = closed_box(0.5, true);
+        for seed in 0..8 {
+            assert!((trace(&scene, seed).r - 1.0).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use once_cell::sync::Lazy;
+use std::ops::{Add, AddAssign, Mul};
+use crate::color::Color;
+
+// Tabla para decodificar canales sRGB de 8 bits a lineal
+static SRGB_TO_LINEAR: Lazy<[f32; 256]> = Lazy::new(|| {
+    let mut table = [0.0; 256];
+    for (i, value) in table.iter_mut().enumerate() {
+        *value = srgb_to_linear(i as f32 / 255.0);
+    }
+    table
+});
+
+// Decodificar un canal sRGB (0..1) a espacio lineal
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Codificar un canal lineal (0..1) a sRGB
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Radiancia RGB en espacio lineal y punto flotante; a diferencia de `Color`
+// no se satura, así que varias luces, reflejos y refracciones suman energía
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Radiance {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl Radiance {
+    pub const BLACK: Radiance = Radiance { r: 0.0, g: 0.0, b: 0.0 };
+
+    pub fn new(r: f32, g: f32, b: f32) -> Self {
+        Radiance { r, g, b }
+    }
+
+    // Convertir un color sRGB de 8 bits a radiancia lineal
+    pub fn from_srgb(color: Color) -> Self {
+        let table = &*SRGB_TO_LINEAR;
+        Radiance {
+            r: table[color.r as usize],
+            g: table[color.g as usize],
+            b: table[color.b as usize],
+        }
+    }
+
+    // Convertir a un color sRGB de 8 bits; los valores fuera de 0..1 se recortan
+    pub fn to_srgb(self) -> Color {
+        let encode = |c: f32| (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0 + 0.5) as u8;
+        Color::new(encode(self.r), encode(self.g), encode(self.b))
+    }
+
+    pub fn scale(self, factor: f32) -> Self {
+        Radiance {
+            r: self.r * factor,
+            g: self.g * factor,
+            b: self.b * factor,
+        }
+    }
+
+    // Luminancia relativa (Rec. 709)
+    pub fn luminance(self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+}
+
+impl Add for Radiance {
+    type Output = Radiance;
+
+    fn add(self, other: Radiance) -> Radiance {
+        Radiance {
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
+        }
+    }
+}
+
+impl AddAssign for Radiance {
+    fn add_assign(&mut self, other: Radiance) {
+        *self = *self + other;
+    }
+}
+
+// Producto componente a componente, p. ej. luz por color difuso
+impl Mul for Radiance {
+    type Output = Radiance;
+
+    fn mul(self, other: Radiance) -> Radiance {
+        Radiance {
+            r: self.r * other.r,
+            g: self.g * other.g,
+            b: self.b * other.b,
+        }
+    }
+}
+
+impl Mul<f32> for Radiance {
+    type Output = Radiance;
+
+    fn mul(self, factor: f32) -> Radiance {
+        self.scale(factor)
+    }
+}
@@ -1,7 +1,11 @@
 use nalgebra::Vector3;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
 use crate::color::Color;
-use crate::texture::Texture;
+use crate::radiance::Radiance;
+use crate::texture::{Texture, TextureFilter, TextureRegion};
 use std::any::Any;
+use crate::bvh::Aabb;
 
 #[derive(Debug, Clone)]
 pub struct Material {
@@ -10,9 +14,19 @@ pub struct Material {
     pub albedo: [f32; 4],
     pub refractive_index: f32,
     pub has_texture: bool,    
-    pub texture: Option<Texture>,
+    pub texture: Option<Arc<Texture>>, // Compartida entre todos los materiales que la usan
+    pub alpha_cutoff: f32,    // Los texels con alfa <= este valor no se intersectan (hojas, rejas)
+    pub filter: TextureFilter, // Cómo se lee la textura
+    pub region: Option<TextureRegion>, // Parte de la textura que usa el material (atlas); `None` = toda
+    pub tint: Option<Color>,  // Color que multiplica la textura (pasto y hojas en escala de grises)
+    pub emission: Color,      // Color de la luz que emite la superficie (glowstone, lava, antorchas)
+    pub emission_strength: f32, // 0 = no emite
+    pub block_light: bool,    // La emisión también está en `Scene::lights` (bloques con `emissive_lights`)
 }
 
+// Material devuelto por las intersecciones vacías
+static BLACK: Lazy<Material> = Lazy::new(Material::black);
+
 impl Material {
     pub fn new(
         diffuse: Color, 
@@ -20,7 +34,7 @@ impl Material {
         albedo: [f32; 4], 
         refractive_index: f32, 
         has_texture: bool, 
-        texture: Option<Texture>  // Pasamos la textura opcional
+        texture: Option<Arc<Texture>>  // Pasamos la textura opcional
     ) -> Self {
         Material {
             diffuse,
@@ -29,9 +43,48 @@ impl Material {
             refractive_index,
             has_texture,
             texture,
+            alpha_cutoff: 0.0,
+            filter: TextureFilter::Nearest,
+            region: None,
+            tint: None,
+            emission: Color::new(0, 0, 0),
+            emission_strength: 0.0,
+            block_light: false,
         }
     }
 
+    pub fn with_alpha_cutoff(mut self, alpha_cutoff: f32) -> Self {
+        self.alpha_cutoff = alpha_cutoff;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_region(mut self, region: TextureRegion) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    pub fn with_emission(mut self, emission: Color, strength: f32) -> Self {
+        self.emission = emission;
+        self.emission_strength = strength;
+        self
+    }
+
+    // Marcar que la emisión ya se muestrea como luz, para que el path tracer no la cuente dos veces
+    pub fn with_block_light(mut self) -> Self {
+        self.block_light = true;
+        self
+    }
+
     pub fn black() -> Self {
         Material {
             diffuse: Color::new(0, 0, 0),
@@ -40,33 +93,88 @@ impl Material {
             refractive_index: 1.0,
             has_texture: false,  // Sin textura
             texture: None,       // No hay textura
+            alpha_cutoff: 0.0,
+            filter: TextureFilter::Nearest,
+            region: None,
+            tint: None,
+            emission: Color::new(0, 0, 0),
+            emission_strength: 0.0,
+            block_light: false,
         }
     }
 
-    // Función para obtener el color difuso, ya sea de una textura o del color base del material
-    pub fn get_diffuse_color(&self, u: f32, v: f32) -> Color {
-        if self.has_texture {
-            // Obtener el color de la textura usando las coordenadas UV
-            self.texture.as_ref().unwrap().get_color(u, v)
+    pub fn is_emissive(&self) -> bool {
+        self.emission_strength > 0.0 && self.emission != Color::new(0, 0, 0)
+    }
+
+    // Luz que emite la superficie (lineal). Con textura, la emisión la multiplica, así que
+    // se ve el dibujo del bloque (las vetas de la glowstone, las burbujas de la lava)
+    pub fn emitted(&self, u: f32, v: f32, footprint: f32) -> Radiance {
+        if !self.is_emissive() {
+            return Radiance::BLACK;
+        }
+        let emission = Radiance::from_srgb(self.emission).scale(self.emission_strength);
+        if self.has_texture && self.texture.is_some() {
+            emission * self.sample_diffuse(u, v, footprint)
         } else {
-            // Retornar el color difuso base
-            self.diffuse
+            emission
+        }
+    }
+
+    // Función para obtener el color difuso (lineal), ya sea de una textura o del color base del material
+    pub fn get_diffuse_color(&self, u: f32, v: f32) -> Radiance {
+        self.sample_diffuse(u, v, 0.0)
+    }
+
+    // Color difuso para un píxel que cubre `footprint` unidades UV; con filtro trilineal
+    // elige el nivel de mipmap según ese tamaño
+    pub fn sample_diffuse(&self, u: f32, v: f32, footprint: f32) -> Radiance {
+        match &self.texture {
+            Some(texture) if self.has_texture => {
+                let region = self.region.unwrap_or_else(|| texture.full_region());
+                let color = texture.sample_region(u, v, &region, self.filter, region.lod(footprint));
+                match self.tint {
+                    Some(tint) => color * Radiance::from_srgb(tint),
+                    None => color,
+                }
+            }
+            _ => Radiance::from_srgb(self.diffuse),
         }
     }
+
+    // Opacidad en las coordenadas UV; los materiales sin textura son opacos
+    pub fn get_alpha(&self, u: f32, v: f32) -> f32 {
+        match &self.texture {
+            Some(texture) if self.has_texture => {
+                let region = self.region.unwrap_or_else(|| texture.full_region());
+                texture.sample_alpha_region(u, v, &region, self.filter, 0.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    // Prueba alfa: el texel es tan transparente que el rayo debe seguir de largo
+    pub fn is_cutout(&self, u: f32, v: f32) -> bool {
+        self.has_texture
+            && self.texture.as_ref().is_some_and(|t| t.has_alpha())
+            && self.get_alpha(u, v) <= self.alpha_cutoff
+    }
 }
 
-pub struct Intersect {
+// Las intersecciones toman prestado el material del objeto en lugar de clonarlo
+pub struct Intersect<'a> {
     pub point: Vector3<f32>,  // Punto de intersección
     pub normal: Vector3<f32>, // Normal en el punto de intersección
     pub distance: f32,        // Distancia desde el origen del rayo
     pub is_intersecting: bool, // Indica si hay una intersección
-    pub material: Material,   // Material del objeto en el punto de intersección
+    pub material: &'a Material, // Material del objeto en el punto de intersección
     pub u: f32,               // Coordenada U para texturas
     pub v: f32,               // Coordenada V para texturas
+    pub uv_scale: f32,        // Unidades UV por unidad de distancia sobre la superficie (1 / lado de la cara)
 }
 
-impl Intersect {
-    pub fn new(point: Vector3<f32>, normal: Vector3<f32>, distance: f32, material: Material, u: f32, v: f32) -> Self {
+impl<'a> Intersect<'a> {
+    pub fn new(point: Vector3<f32>, normal: Vector3<f32>, distance: f32, material: &'a Material, u: f32, v: f32) -> Self {
         Intersect {
             point,
             normal,
@@ -75,26 +183,41 @@ impl Intersect {
             material,
             u,
             v,
+            uv_scale: 1.0,
         }
     }
 
+    pub fn with_uv_scale(mut self, uv_scale: f32) -> Self {
+        self.uv_scale = uv_scale;
+        self
+    }
+
     pub fn empty() -> Self {
         Intersect {
             point: Vector3::zeros(),
             normal: Vector3::zeros(),
             distance: 0.0,
             is_intersecting: false,
-            material: Material::black(),
+            material: &BLACK,
             u: 0.0,
             v: 0.0,
+            uv_scale: 1.0,
         }
     }
 }
 
-pub trait RayIntersect: Any {
-    fn ray_intersect(&self, ray_origin: &Vector3<f32>, ray_direction: &Vector3<f32>) -> Intersect;
-    fn get_uv(&self, point: &Vector3<f32>) -> (f32, f32);
+pub trait RayIntersect: Any + Send + Sync {
+    fn ray_intersect(&self, ray_origin: &Vector3<f32>, ray_direction: &Vector3<f32>) -> Intersect<'_>;
+    fn bounding_box(&self) -> Aabb;
     fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    // ¿Tapa el objeto el rayo antes de la distancia `max_t`? Es la consulta de los rayos de
+    // sombra: basta un sí o un no. Un rayo que sale desde dentro del objeto no queda tapado
+    // por él, así que los objetos que pueden contener el origen deben sobreescribirla
+    fn occludes(&self, ray_origin: &Vector3<f32>, ray_direction: &Vector3<f32>, max_t: f32) -> bool {
+        let intersect = self.ray_intersect(ray_origin, ray_direction);
+        intersect.is_intersecting && intersect.distance < max_t
+    }
 }
 
 /*
@@ -1,72 +1,272 @@
 use nalgebra::Vector3;
 use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use std::str::FromStr;
+use std::sync::Arc;
+use crate::antialiasing::{supersample, PixelFilter, SamplePattern};
 use crate::framebuffer::Framebuffer;
-use crate::ray_intersect::{Intersect, RayIntersect, Material};
-use crate::camera::Camera;
+use crate::path_tracer::trace_path;
+use crate::ray_intersect::Intersect;
 use crate::light::Light;
-use crate::color::Color;
+use crate::radiance::Radiance;
+use crate::sampling::Sampler;
+use crate::scene::Scene;
+use crate::tonemap::ToneMapper;
 
-pub fn render(framebuffer: &mut Framebuffer, objects: &[Box<dyn RayIntersect>], camera: &Camera, lights: &[Light]) {
-    let width = framebuffer.width;
-    let height = framebuffer.height;
-    let aspect_ratio = width as f32 / height as f32;
-    let chunk_size = 8;
+// Rayos de sombra por luz con radio si no se indica otra cosa
+pub const DEFAULT_SHADOW_SAMPLES: u32 = 16;
 
-    framebuffer.buffer.chunks_mut(width * chunk_size).enumerate().for_each(|(chunk_idx, chunk)| {
-        let base_y = chunk_idx * chunk_size;
+// Fracción de cada luz que llega como luz ambiente, esté o no el punto en sombra. Reemplaza
+// a la luz indirecta que este trazador no calcula, para que las sombras no queden totalmente negras
+const AMBIENT_LIGHT: f32 = 0.2;
 
-        for (y, row) in chunk.chunks_mut(width).enumerate() {
-            let screen_y = -((2.0 * (base_y + y) as f32) / height as f32 - 1.0);
+// Rebotes del path tracer si no se indica otra cosa
+pub const DEFAULT_MAX_BOUNCES: u32 = 5;
 
-            row.iter_mut().enumerate().for_each(|(x, pixel)| {
-                let screen_x = (2.0 * x as f32) / width as f32 - 1.0;
-                let screen_x = screen_x * aspect_ratio;
+// Cómo se calcula la luz de cada píxel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integrator {
+    // Reflejos y refracciones exactos y luz directa con Phong: un cuadro ya terminado por render
+    Whitted,
+    // Monte Carlo con iluminación global: cada render suma una muestra por píxel al
+    // acumulador del framebuffer y la imagen se limpia con las pasadas
+    PathTracing { max_bounces: u32 },
+}
 
-                let ray_direction = camera.base_change(&Vector3::new(screen_x, screen_y, -1.0).normalize());
+impl FromStr for Integrator {
+    type Err = String;
 
-                let mut pixel_color = Color::new(0, 0, 0);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "whitted" => Ok(Integrator::Whitted),
+            "path" | "pathtracing" => Ok(Integrator::PathTracing { max_bounces: DEFAULT_MAX_BOUNCES }),
+            _ => Err(format!("integrador desconocido: {} (whitted, path)", s)),
+        }
+    }
+}
 
-                for light in lights {
-                    let light_contrib = cast_ray(&camera.eye, &ray_direction, objects, light, 0);
-                    pixel_color.r = (pixel_color.r + light_contrib.r).min(255);
-                    pixel_color.g = (pixel_color.g + light_contrib.g).min(255);
-                    pixel_color.b = (pixel_color.b + light_contrib.b).min(255);
-                }
+// Opciones del render: tamaño de los bloques de filas, pool de hilos de rayon, tone mapping,
+// calidad de las sombras suaves, integrador, si se acumulan las pasadas, antialiasing y
+// profundidad de campo
+#[derive(Clone)]
+pub struct RenderOptions {
+    pub tile_size: usize,                     // Filas por bloque de trabajo
+    pub thread_pool: Option<Arc<ThreadPool>>, // `None` usa el pool global de rayon
+    pub tone_mapper: ToneMapper,              // Conversión de la radiancia HDR a píxeles sRGB
+    pub shadow_samples: u32,                  // Rayos de sombra hacia cada luz con radio (se redondea a un cuadrado)
+    pub integrator: Integrator,
+    pub accumulate: bool,                     // Sumar cada render al acumulador (el path tracer siempre lo hace)
+    pub sampling: SamplePattern,              // Rayos por píxel
+    pub filter: PixelFilter,                  // Cómo se combinan las muestras en los píxeles
+    pub depth_of_field: bool,                 // Muestrear la lente de la cámara si tiene apertura
+}
 
-                *pixel = ((pixel_color.r as u32) << 16)
-                    | ((pixel_color.g as u32) << 8)
-                    | (pixel_color.b as u32);
-            });
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            tile_size: 8,
+            thread_pool: None,
+            tone_mapper: ToneMapper::default(),
+            shadow_samples: DEFAULT_SHADOW_SAMPLES,
+            integrator: Integrator::Whitted,
+            accumulate: false,
+            sampling: SamplePattern::Center,
+            filter: PixelFilter::Box,
+            depth_of_field: true,
         }
-    });
+    }
 }
 
-fn cast_shadow(
-    intersect: &Intersect,
-    light: &Light,
-    objects: &[Box<dyn RayIntersect>],
-) -> f32 {
-    let light_dir = (light.position - intersect.point).normalize();
-    
-    let shadow_ray_origin = intersect.point + intersect.normal * 1e-2;
-    let mut shadow_intensity = 0.0;
-
-    for object in objects {
-        let shadow_intersect = object.ray_intersect(&shadow_ray_origin, &light_dir);
-        if shadow_intersect.is_intersecting {
-            let distance_to_light = (light.position - intersect.point).magnitude();
-            let shadow_distance = (shadow_intersect.point - shadow_ray_origin).magnitude();
-            
-            shadow_intensity = (0.6 - (shadow_distance / distance_to_light)).max(0.0);
-            break;
+impl RenderOptions {
+    // Usar un pool propio con `threads` hilos (0 = uno por núcleo)
+    pub fn with_threads(mut self, threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+        self.thread_pool = Some(Arc::new(pool));
+        Ok(self)
+    }
+
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    pub fn with_tone_mapper(mut self, tone_mapper: ToneMapper) -> Self {
+        self.tone_mapper = tone_mapper;
+        self
+    }
+
+    // Con 1 las luces con radio dan sombras duras desde su centro
+    pub fn with_shadow_samples(mut self, shadow_samples: u32) -> Self {
+        self.shadow_samples = shadow_samples.max(1);
+        self
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    // Con acumulación, cada render del trazador de Whitted también suma una muestra en un
+    // punto al azar del píxel, así que la imagen quieta converge a una sin dientes de sierra
+    pub fn with_accumulation(mut self, accumulate: bool) -> Self {
+        self.accumulate = accumulate;
+        self
+    }
+
+    pub fn with_sampling(mut self, sampling: SamplePattern) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: PixelFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    // Sin profundidad de campo todo sale enfocado aunque la cámara tenga apertura (la vista
+    // previa del visor, con un solo rayo por píxel, quedaría llena de ruido)
+    pub fn with_depth_of_field(mut self, depth_of_field: bool) -> Self {
+        self.depth_of_field = depth_of_field;
+        self
+    }
+}
+
+// Cono que envuelve los rayos de un píxel: su ancho en el origen y cuánto crece por unidad
+// de distancia. Con él se estima cuánta superficie cubre el píxel para elegir el mipmap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayCone {
+    pub width: f32,
+    pub spread: f32,
+}
+
+impl RayCone {
+    // Rayo sin grosor: las texturas se leen siempre en el nivel base
+    pub const POINT: RayCone = RayCone { width: 0.0, spread: 0.0 };
+
+    pub fn width_at(&self, distance: f32) -> f32 {
+        self.width + self.spread * distance
+    }
+
+    // Cono que sigue desde un punto a `distance`; las caras son planas, así que conserva la apertura
+    pub fn propagate(&self, distance: f32) -> RayCone {
+        RayCone {
+            width: self.width_at(distance),
+            spread: self.spread,
         }
     }
+}
+
+pub fn render(framebuffer: &mut Framebuffer, scene: &Scene) {
+    render_with_options(framebuffer, scene, &RenderOptions::default());
+}
 
-    shadow_intensity
+pub fn render_with_options(framebuffer: &mut Framebuffer, scene: &Scene, options: &RenderOptions) {
+    match &options.thread_pool {
+        Some(pool) => pool.install(|| render_tiles(framebuffer, scene, options)),
+        None => render_tiles(framebuffer, scene, options),
+    }
 }
 
-fn refract(incident: &Vector3<f32>, normal: &Vector3<f32>, eta_t: f32) -> Vector3<f32> {
-    let cosi = -incident.dot(normal).max(-1.0).min(1.0);
+// Cada bloque de filas se renderiza en paralelo; cada píxel es independiente, así que
+// el resultado es el mismo sin importar el número de hilos. Al acumular, la muestra de esta
+// pasada se suma al acumulador y el píxel muestra el promedio de todas. Con un rayo por píxel,
+// la primera pasada pasa por el centro del píxel y las siguientes por puntos al azar dentro
+// de él; con supersampling, cada pasada lanza los rayos del patrón y los combina con el filtro
+fn render_tiles(framebuffer: &mut Framebuffer, scene: &Scene, options: &RenderOptions) {
+    let camera = &scene.camera;
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let chunk_size = options.tile_size.max(1);
+    let tone_mapper = &options.tone_mapper;
+    let shadow_samples = options.shadow_samples;
+    let lights = scene.active_lights();
+    let integrator = options.integrator;
+    let accumulate = options.accumulate || matches!(integrator, Integrator::PathTracing { .. });
+    let pass = if accumulate { framebuffer.samples } else { 0 };
+    // Cuánta superficie cubre un píxel, para elegir el mipmap de las texturas
+    let pixel_cone = camera.ray_cone(height);
+    let depth_of_field = options.depth_of_field && camera.has_depth_of_field();
+    // Radiancia del rayo que pasa por el punto (x, y) de la pantalla, en píxeles; con profundidad
+    // de campo, cada rayo sale de un punto distinto de la lente
+    let trace = |x: f32, y: f32, sampler: &mut Sampler| {
+        let (ray_origin, ray_direction) = if depth_of_field {
+            let (lens_u, lens_v) = (sampler.next_f32(), sampler.next_f32());
+            camera.generate_lens_ray(x, y, width, height, lens_u, lens_v)
+        } else {
+            camera.generate_ray(x, y, width, height)
+        };
+        match integrator {
+            Integrator::Whitted => cast_ray_cone(&ray_origin, &ray_direction, scene, &lights, 0, pixel_cone, shadow_samples),
+            Integrator::PathTracing { max_bounces } => {
+                trace_path(&ray_origin, &ray_direction, scene, &lights, sampler, max_bounces, pixel_cone)
+            }
+        }
+    };
+    let supersampled = (options.sampling != SamplePattern::Center)
+        .then(|| supersample(width, height, options.sampling, options.filter, pass, trace));
+
+    let pixels = framebuffer.buffer.par_chunks_mut(width * chunk_size);
+    let radiance = framebuffer.radiance.par_chunks_mut(width * chunk_size);
+    let accumulation = framebuffer.accumulation.par_chunks_mut(width * chunk_size);
+    pixels.zip(radiance).zip(accumulation).enumerate().for_each(|(chunk_idx, ((chunk, hdr_chunk), sum_chunk))| {
+        let base_y = chunk_idx * chunk_size;
+
+        let rows = chunk.chunks_mut(width).zip(hdr_chunk.chunks_mut(width)).zip(sum_chunk.chunks_mut(width));
+        for (y, ((row, hdr_row), sum_row)) in rows.enumerate() {
+            let y = base_y + y;
+
+            for (x, ((pixel, hdr), sum)) in row.iter_mut().zip(hdr_row.iter_mut()).zip(sum_row.iter_mut()).enumerate() {
+                let sample = match &supersampled {
+                    Some(filtered) => filtered[y * width + x],
+                    None if pass == 0 => trace(x as f32, y as f32, &mut Sampler::for_pixel(x, y, pass)),
+                    None => {
+                        let mut sampler = Sampler::for_pixel(x, y, pass);
+                        let (dx, dy) = (sampler.next_f32() - 0.5, sampler.next_f32() - 0.5);
+                        trace(x as f32 + dx, y as f32 + dy, &mut sampler)
+                    }
+                };
+                let pixel_color = if accumulate {
+                    *sum = if pass == 0 { sample } else { *sum + sample };
+                    sum.scale(1.0 / (pass + 1) as f32)
+                } else {
+                    sample
+                };
+
+                *hdr = pixel_color;
+                *pixel = tone_mapper.to_pixel(pixel_color);
+            }
+        }
+    });
+
+    framebuffer.samples = if accumulate { pass + 1 } else { 0 };
+}
+
+// Fracción de la luz que llega al punto, en [0, 1]. Las luces con radio se muestrean en
+// puntos estratificados de su disco, así que un punto que ve solo parte de la luz queda
+// en penumbra. La semilla sale del punto: el resultado no cambia entre cuadros ni hilos.
+// Los rayos se detienen en la superficie de la esfera de la luz, así que una luz dentro de
+// un bloque emisivo no queda tapada por su propio bloque
+fn light_visibility(intersect: &Intersect, light: &Light, scene: &Scene, samples: u32) -> f32 {
+    let shadow_ray_origin = intersect.point + intersect.normal * 1e-2;
+    let points = if light.radius > 0.0 && samples > 1 {
+        Sampler::from_point(&intersect.point).stratified(samples)
+    } else {
+        vec![(0.5, 0.5)]
+    };
+
+    let visible = points
+        .iter()
+        .filter(|&&(u, v)| {
+            let (light_dir, distance_to_light) = light.sample_direction(&shadow_ray_origin, u, v);
+            !scene.occluded(&shadow_ray_origin, &light_dir, distance_to_light - light.radius)
+        })
+        .count();
+
+    visible as f32 / points.len() as f32
+}
+
+pub(crate) fn refract(incident: &Vector3<f32>, normal: &Vector3<f32>, eta_t: f32) -> Vector3<f32> {
+    let cosi = -incident.dot(normal).clamp(-1.0, 1.0);
 
     let (n_cosi, eta, n_normal);
 
@@ -92,68 +292,200 @@ fn refract(incident: &Vector3<f32>, normal: &Vector3<f32>, eta_t: f32) -> Vector
     }
 }
 
+// Radiancia que llega por el rayo. El camino (reflejos, refracciones) se traza una sola vez
+// y en cada choque se suma la luz directa de todas las luces
 pub fn cast_ray(
     ray_origin: &Vector3<f32>,
     ray_direction: &Vector3<f32>,
-    objects: &[Box<dyn RayIntersect>],
-    light: &Light,
+    scene: &Scene,
+    lights: &[Light],
+    depth: u32,
+) -> Radiance {
+    cast_ray_cone(ray_origin, ray_direction, scene, lights, depth, RayCone::POINT, DEFAULT_SHADOW_SAMPLES)
+}
+
+// Igual que `cast_ray`, pero sabiendo el grosor del rayo para filtrar las texturas
+// y con cuántos rayos de sombra muestrear las luces con radio
+pub fn cast_ray_cone(
+    ray_origin: &Vector3<f32>,
+    ray_direction: &Vector3<f32>,
+    scene: &Scene,
+    lights: &[Light],
     depth: u32,
-) -> Color {
+    cone: RayCone,
+    shadow_samples: u32,
+) -> Radiance {
     if depth > 3 {
-        return Color::new(0, 0, 0);  // Color de fondo si alcanzamos la profundidad máxima
+        return Radiance::BLACK;  // Color de fondo si alcanzamos la profundidad máxima
     }
 
-    let mut closest_intersect = Intersect::empty();
-    let mut zbuffer = f32::INFINITY;
-
-    for object in objects {
-        let intersect = object.ray_intersect(ray_origin, ray_direction);
-        if intersect.is_intersecting && intersect.distance < zbuffer {
-            zbuffer = intersect.distance;
-            closest_intersect = intersect;
-        }
-    }
+    let closest_intersect = scene.intersect(ray_origin, ray_direction);
 
     if !closest_intersect.is_intersecting {
-        return Color::new(4, 12, 36);  // Color de fondo
+        return scene.background(&ray_direction.normalize());
     }
 
-    let diffuse_color = closest_intersect.material.get_diffuse_color(closest_intersect.u, closest_intersect.v);
-    let light_dir = (light.position - closest_intersect.point).normalize();
-    let view_dir = (ray_origin - closest_intersect.point).normalize();
-    let reflect_dir = reflect(&-light_dir, &closest_intersect.normal);
-
-    let shadow_intensity = cast_shadow(&closest_intersect, light, objects);
-    let light_intensity = light.intensity * (1.0 - shadow_intensity);
-
-    let diffuse_intensity = light_dir.dot(&closest_intersect.normal).max(0.0).min(1.0);
-    let diffuse = diffuse_color.scale(closest_intersect.material.albedo[0] * diffuse_intensity * light_intensity);
+    let footprint = texture_footprint(&cone, ray_direction, &closest_intersect);
+    let secondary_cone = cone.propagate(closest_intersect.distance);
 
-    let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(closest_intersect.material.specular);
-    let specular = light.color.scale(closest_intersect.material.albedo[1] * specular_intensity * light_intensity);
+    let diffuse_color = closest_intersect.material.sample_diffuse(closest_intersect.u, closest_intersect.v, footprint);
+    let view_dir = (ray_origin - closest_intersect.point).normalize();
+    let direct = lights.iter().fold(Radiance::BLACK, |sum, light| {
+        sum + direct_light(&closest_intersect, &diffuse_color, &view_dir, light, scene, shadow_samples)
+    });
 
-    let mut reflect_color = Color::new(0, 0, 0);
+    let mut reflect_color = Radiance::BLACK;
     let reflectivity = closest_intersect.material.albedo[2];
     if reflectivity > 0.0 {
         let reflect_dir = reflect(&-ray_direction, &closest_intersect.normal).normalize();
         let reflect_origin = closest_intersect.point + closest_intersect.normal * 1e-3;
-        reflect_color = cast_ray(&reflect_origin, &reflect_dir, objects, light, depth + 1);
+        reflect_color = cast_ray_cone(&reflect_origin, &reflect_dir, scene, lights, depth + 1, secondary_cone, shadow_samples);
     }
 
-    let mut refract_color = Color::new(0, 0, 0);
+    let mut refract_color = Radiance::BLACK;
     let transparency = closest_intersect.material.albedo[3];
     if transparency > 0.0 {
-        let refract_dir = refract(&ray_direction, &closest_intersect.normal, closest_intersect.material.refractive_index).normalize();
+        let refract_dir = refract(ray_direction, &closest_intersect.normal, closest_intersect.material.refractive_index).normalize();
         let refract_origin = closest_intersect.point - closest_intersect.normal * 1e-3;
-        refract_color = cast_ray(&refract_origin, &refract_dir, objects, light, depth + 1);
+        refract_color = cast_ray_cone(&refract_origin, &refract_dir, scene, lights, depth + 1, secondary_cone, shadow_samples);
     }
 
-    (diffuse + specular)
+    let emitted = closest_intersect.material.emitted(closest_intersect.u, closest_intersect.v, footprint);
+    let surface_color = direct
         .scale(1.0 - reflectivity - transparency)
+        + emitted
         + reflect_color.scale(reflectivity)
-        + refract_color.scale(transparency)
+        + refract_color.scale(transparency);
+
+    // Alfa parcial (vidrio de colores): mezclamos con lo que hay detrás siguiendo el mismo rayo
+    let alpha = closest_intersect.material.get_alpha(closest_intersect.u, closest_intersect.v);
+    if alpha < 1.0 {
+        let behind_origin = closest_intersect.point + ray_direction * 1e-3;
+        let behind_color = cast_ray_cone(&behind_origin, ray_direction, scene, lights, depth + 1, secondary_cone, shadow_samples);
+        return surface_color.scale(alpha) + behind_color.scale(1.0 - alpha);
+    }
+
+    surface_color
 }
 
-fn reflect(incident: &Vector3<f32>, normal: &Vector3<f32>) -> Vector3<f32> {
+// Unidades UV que cubre el píxel en el punto de intersección, estiradas cuando el rayo
+// llega rasante; de aquí sale el nivel de mipmap
+fn texture_footprint(cone: &RayCone, ray_direction: &Vector3<f32>, intersect: &Intersect) -> f32 {
+    let cos_incidence = ray_direction.normalize().dot(&intersect.normal).abs().max(0.05);
+    cone.width_at(intersect.distance) / cos_incidence * intersect.uv_scale
+}
+
+// Ambiente, difuso y especular (Phong) que aporta una luz en el punto de choque
+fn direct_light(
+    intersect: &Intersect,
+    diffuse_color: &Radiance,
+    view_dir: &Vector3<f32>,
+    light: &Light,
+    scene: &Scene,
+    shadow_samples: u32,
+) -> Radiance {
+    // Sin rayos de sombra para los puntos fuera del cono o del alcance de la luz
+    let incoming = light.intensity_at(&intersect.point);
+    if incoming <= 0.0 {
+        return Radiance::BLACK;
+    }
+    // El color de la luz tiñe el difuso, la luz ambiente y el brillo especular (el sol anaranjado del atardecer)
+    let light_color = Radiance::from_srgb(light.color);
+    let ambient = (*diffuse_color * light_color).scale(intersect.material.albedo[0] * AMBIENT_LIGHT * incoming);
+
+    let (light_dir, _) = light.direction_from(&intersect.point);
+    let diffuse_intensity = light_dir.dot(&intersect.normal).clamp(0.0, 1.0);
+    let reflect_dir = reflect(&-light_dir, &intersect.normal);
+    let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(intersect.material.specular);
+    // Tampoco hacen falta si la luz no aporta nada más (por ejemplo, si llega por detrás de la cara)
+    if diffuse_intensity <= 0.0 && specular_intensity <= 0.0 {
+        return ambient;
+    }
+
+    let light_intensity = incoming * light_visibility(intersect, light, scene, shadow_samples);
+    let diffuse = (*diffuse_color * light_color).scale(intersect.material.albedo[0] * diffuse_intensity * light_intensity);
+    let specular = light_color.scale(intersect.material.albedo[1] * specular_intensity * light_intensity);
+    ambient + diffuse + specular
+}
+
+pub(crate) fn reflect(incident: &Vector3<f32>, normal: &Vector3<f32>) -> Vector3<f32> {
     incident - 2.0 * incident.dot(normal) * normal
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::ray_intersect::Material;
+    use nalgebra_glm::Vec3;
+
+    #[test]
+    fn footprint_grows_with_distance_and_grazing_angles() {
+        let material = Material::black();
+        let cone = RayCone { width: 0.0, spread: 0.01 };
+        let normal = Vector3::new(0.0, 0.0, -1.0);
+        let hit = |distance: f32| Intersect::new(Vector3::zeros(), normal, distance, &material, 0.0, 0.0);
+
+        // De frente, el píxel cubre `spread * distancia` sobre la cara
+        let head_on = Vector3::new(0.0, 0.0, 1.0);
+        assert!((texture_footprint(&cone, &head_on, &hit(10.0)) - 0.1).abs() < 1e-6);
+        assert!((texture_footprint(&cone, &head_on, &hit(20.0)) - 0.2).abs() < 1e-6);
+        // Un bloque de lado 0.5 tiene el doble de UV por unidad de distancia
+        assert!((texture_footprint(&cone, &head_on, &hit(10.0).with_uv_scale(2.0)) - 0.2).abs() < 1e-6);
+        // A 60° de la normal la huella se estira al doble
+        let oblique = Vector3::new(3.0f32.sqrt(), 0.0, 1.0);
+        assert!((texture_footprint(&cone, &oblique, &hit(10.0)) - 0.2).abs() < 1e-5);
+        // Un rayo sin grosor siempre lee el nivel base
+        assert_eq!(texture_footprint(&RayCone::POINT, &head_on, &hit(10.0)), 0.0);
+    }
+
+    #[test]
+    fn cones_keep_their_spread_after_a_bounce() {
+        let cone = RayCone { width: 0.5, spread: 0.01 };
+        let bounced = cone.propagate(100.0);
+        assert!((bounced.width - 1.5).abs() < 1e-6);
+        assert_eq!(bounced.spread, 0.01);
+    }
+    // Luz de radio 2 a 10 unidades sobre el origen, con un bloqueador de lado 6 a media altura
+    fn blocked_scene(blocker_x: f32, radius: f32) -> Scene {
+        let material = Arc::new(Material::black());
+        let blocker = Cube::new(Vec3::new(blocker_x, 5.0, 0.0), 6.0, std::array::from_fn(|_| material.clone()));
+        let light = Light::new(Vector3::new(0.0, 10.0, 0.0), Color::new(255, 255, 255), 1.0, radius);
+        let camera = Camera::new(Vec3::new(0.0, 1.0, -5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        Scene::new(vec![Box::new(blocker)], vec![light], camera)
+    }
+
+    fn visibility(scene: &Scene, samples: u32) -> f32 {
+        let material = Material::black();
+        let floor = Intersect::new(Vector3::zeros(), Vector3::new(0.0, 1.0, 0.0), 1.0, &material, 0.0, 0.0);
+        light_visibility(&floor, &scene.lights[0], scene, samples)
+    }
+
+    #[test]
+    fn half_covered_lights_give_a_penumbra() {
+        // El bloqueador llega justo hasta x = 0: tapa la mitad del disco de la luz
+        let half = visibility(&blocked_scene(-3.0, 2.0), 16);
+        assert!(half > 0.0 && half < 1.0, "{}", half);
+        assert!((half - 0.5).abs() <= 0.25, "{}", half);
+        // Con un solo rayo la sombra vuelve a ser dura
+        let single = visibility(&blocked_scene(-3.0, 2.0), 1);
+        assert!(single == 0.0 || single == 1.0, "{}", single);
+    }
+
+    #[test]
+    fn fully_blocked_and_clear_lights() {
+        assert_eq!(visibility(&blocked_scene(0.0, 2.0), 16), 0.0);
+        assert_eq!(visibility(&blocked_scene(0.0, 0.0), 16), 0.0);
+        assert_eq!(visibility(&blocked_scene(20.0, 2.0), 16), 1.0);
+    }
+
+    #[test]
+    fn shadows_only_keep_the_ambient_light() {
+        let white = Arc::new(Material::new(Color::new(255, 255, 255), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0, false, None));
+        let mut scene = blocked_scene(0.0, 0.0);
+        scene.add_object(Cube::new(Vec3::new(0.0, -0.5, 0.0), 1.0, std::array::from_fn(|_| white.clone())));
+        let color = cast_ray(&Vector3::new(0.0, 1.0, 0.0), &Vector3::new(0.0, -1.0, 0.0), &scene, &scene.lights, 0);
+        assert!((color.r - AMBIENT_LIGHT).abs() < 1e-5, "{:?}", color);
+    }
+}
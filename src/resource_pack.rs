@@ -0,0 +1,609 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::color::Color;
+use crate::radiance::Radiance;
+use crate::ray_intersect::Material;
+use crate::texture::{open_image, ColorSpace, Texture, TextureRegion};
+
+// Límite de padres que seguimos al resolver un modelo, para cortar ciclos
+const MAX_MODEL_DEPTH: usize = 32;
+
+// Colores de bioma por defecto (llanuras) para las texturas en escala de grises
+const GRASS_TINT: Color = Color { r: 145, g: 189, b: 89 };
+const FOLIAGE_TINT: Color = Color { r: 119, g: 171, b: 47 };
+const SPRUCE_TINT: Color = Color { r: 97, g: 153, b: 97 };
+const BIRCH_TINT: Color = Color { r: 128, g: 167, b: 85 };
+const WATER_TINT: Color = Color { r: 63, g: 118, b: 228 };
+
+// Modelos padre de vanilla que los resource packs casi nunca incluyen
+const BUILTIN_MODELS: &[(&str, &str)] = &[
+    ("minecraft:block/block", r#"{}"#),
+    (
+        "minecraft:block/cube",
+        r##"{ "parent": "block/block", "elements": [ { "from": [0, 0, 0], "to": [16, 16, 16], "faces": {
+            "down": { "texture": "#down" }, "up": { "texture": "#up" },
+            "north": { "texture": "#north" }, "south": { "texture": "#south" },
+            "west": { "texture": "#west" }, "east": { "texture": "#east" } } } ] }"##,
+    ),
+    (
+        "minecraft:block/cube_all",
+        r##"{ "parent": "block/cube", "textures": { "particle": "#all", "down": "#all", "up": "#all",
+            "north": "#all", "south": "#all", "west": "#all", "east": "#all" } }"##,
+    ),
+    (
+        "minecraft:block/cube_column",
+        r##"{ "parent": "block/cube", "textures": { "particle": "#side", "down": "#end", "up": "#end",
+            "north": "#side", "south": "#side", "west": "#side", "east": "#side" } }"##,
+    ),
+    (
+        "minecraft:block/cube_bottom_top",
+        r##"{ "parent": "block/cube", "textures": { "particle": "#side", "down": "#bottom", "up": "#top",
+            "north": "#side", "south": "#side", "west": "#side", "east": "#side" } }"##,
+    ),
+    (
+        "minecraft:block/cube_top",
+        r##"{ "parent": "block/cube", "textures": { "particle": "#side", "down": "#side", "up": "#top",
+            "north": "#side", "south": "#side", "west": "#side", "east": "#side" } }"##,
+    ),
+    (
+        "minecraft:block/leaves",
+        r##"{ "parent": "block/block", "textures": { "particle": "#all" }, "elements": [ { "from": [0, 0, 0], "to": [16, 16, 16], "faces": {
+            "down": { "texture": "#all", "tintindex": 0 }, "up": { "texture": "#all", "tintindex": 0 },
+            "north": { "texture": "#all", "tintindex": 0 }, "south": { "texture": "#all", "tintindex": 0 },
+            "west": { "texture": "#all", "tintindex": 0 }, "east": { "texture": "#all", "tintindex": 0 } } } ] }"##,
+    ),
+];
+
+// Nombre de cada cara en los modelos, en el orden de caras de `Cube` (-X, +X, -Y, +Y, -Z, +Z)
+const FACE_NAMES: [&str; 6] = ["west", "east", "down", "up", "north", "south"];
+
+// Textura y tinte de cada cara de un bloque, antes de armar el atlas
+type FaceTextures = [(String, Option<Color>); 6];
+// Lo mismo, con el índice de la textura en el atlas
+type FaceTiles = [(usize, Option<Color>); 6];
+
+// Errores al leer un resource pack
+#[derive(Debug)]
+pub enum ResourcePackError {
+    NotAResourcePack { path: PathBuf },
+    Io { path: PathBuf, source: std::io::Error },
+    Parse { path: PathBuf, source: serde_json::Error },
+}
+
+impl fmt::Display for ResourcePackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourcePackError::NotAResourcePack { path } => {
+                write!(f, "{} no es un resource pack (falta assets/minecraft)", path.display())
+            }
+            ResourcePackError::Io { path, source } => {
+                write!(f, "no se pudo leer {}: {}", path.display(), source)
+            }
+            ResourcePackError::Parse { path, source } => {
+                write!(f, "modelo inválido en {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResourcePackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResourcePackError::NotAResourcePack { .. } => None,
+            ResourcePackError::Io { source, .. } => Some(source),
+            ResourcePackError::Parse { source, .. } => Some(source),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ModelDesc {
+    parent: Option<String>,
+    #[serde(default)]
+    textures: HashMap<String, String>,
+    elements: Option<Vec<ElementDesc>>,
+}
+
+#[derive(Deserialize)]
+struct ElementDesc {
+    from: [f32; 3],
+    to: [f32; 3],
+    #[serde(default)]
+    faces: HashMap<String, FaceDesc>,
+}
+
+#[derive(Deserialize)]
+struct FaceDesc {
+    texture: String,
+    tintindex: Option<i32>,
+}
+
+// Cara de un bloque dentro del atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockFace {
+    pub region: TextureRegion,
+    pub tint: Option<Color>, // Color de bioma para las caras que Minecraft tiñe
+}
+
+// Texturas de bloques de un resource pack con la estructura de Minecraft
+// (`assets/minecraft/models/block/*.json` y `assets/minecraft/textures/block/*.png`),
+// empaquetadas en un único atlas compartido por todos los materiales
+pub struct ResourcePack {
+    atlas: Arc<Texture>,
+    blocks: HashMap<String, [BlockFace; 6]>,
+    missing: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl ResourcePack {
+    // Resolver los modelos de `block_names` (p. ej. "grass_block", "minecraft:oak_log")
+    // y cargar solo las texturas que usan. Los bloques sin modelo ni texturas en el pack
+    // quedan en `missing()` en lugar de fallar, para poder usar un material de respaldo
+    pub fn load<P: AsRef<Path>>(root: P, block_names: &[&str]) -> Result<Self, ResourcePackError> {
+        let root = root.as_ref();
+        if !root.join("assets").join("minecraft").is_dir() {
+            return Err(ResourcePackError::NotAResourcePack { path: root.to_path_buf() });
+        }
+
+        let mut resolved: Vec<(String, FaceTextures)> = Vec::new();
+        let mut missing = Vec::new();
+        for &name in block_names {
+            let name = block_id(name);
+            if resolved.iter().any(|(n, _)| *n == name) || missing.contains(&name) {
+                continue;
+            }
+            match resolve_block(root, &name)? {
+                Some(faces) => resolved.push((name, faces)),
+                None => missing.push(name),
+            }
+        }
+
+        // Cargamos cada textura una vez; si falta alguna, el bloque entero queda sin resolver
+        let mut tiles: Vec<Texture> = Vec::new();
+        let mut tile_index: HashMap<String, usize> = HashMap::new();
+        let mut face_tiles: Vec<(String, FaceTiles)> = Vec::new();
+        let mut warnings = Vec::new();
+        'blocks: for (name, faces) in resolved {
+            let mut indices = [(0, None); 6];
+            for (face, (texture, tint)) in faces.iter().enumerate() {
+                let index = match tile_index.get(texture) {
+                    Some(&index) => index,
+                    None => match load_tile(root, texture, &mut warnings) {
+                        Some(tile) => {
+                            tiles.push(tile);
+                            tile_index.insert(texture.clone(), tiles.len() - 1);
+                            tiles.len() - 1
+                        }
+                        None => {
+                            missing.push(name);
+                            continue 'blocks;
+                        }
+                    },
+                };
+                indices[face] = (index, *tint);
+            }
+            face_tiles.push((name, indices));
+        }
+
+        let (atlas, regions) = build_atlas(&tiles);
+        let blocks = face_tiles
+            .into_iter()
+            .map(|(name, faces)| {
+                let faces = faces.map(|(index, tint)| BlockFace { region: regions[index], tint });
+                (name, faces)
+            })
+            .collect();
+
+        Ok(ResourcePack {
+            atlas: Arc::new(atlas),
+            blocks,
+            missing,
+            warnings,
+        })
+    }
+
+    pub fn atlas(&self) -> &Arc<Texture> {
+        &self.atlas
+    }
+
+    pub fn block_faces(&self, name: &str) -> Option<&[BlockFace; 6]> {
+        self.blocks.get(&block_id(name))
+    }
+
+    // Bloques pedidos que el pack no tiene
+    pub fn missing(&self) -> &[String] {
+        &self.missing
+    }
+
+    // Texturas del pack que no se pudieron decodificar y se reemplazaron por un tablero magenta
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    // Materiales de las seis caras de un bloque; brillo, albedo y filtro salen de `base`
+    pub fn block_materials(&self, name: &str, base: &Material) -> Option<[Arc<Material>; 6]> {
+        let faces = self.block_faces(name)?;
+        let mut built: Vec<(BlockFace, Arc<Material>)> = Vec::with_capacity(6);
+        Some(faces.map(|face| {
+            // Las caras iguales comparten material
+            if let Some((_, material)) = built.iter().find(|(f, _)| *f == face) {
+                return Arc::clone(material);
+            }
+            let mut material = base.clone();
+            material.texture = Some(Arc::clone(&self.atlas));
+            material.has_texture = true;
+            material.region = Some(face.region);
+            material.tint = face.tint;
+            let material = Arc::new(material);
+            built.push((face, Arc::clone(&material)));
+            material
+        }))
+    }
+}
+
+// "grass_block" y "minecraft:grass_block" son el mismo bloque
+fn block_id(name: &str) -> String {
+    name.strip_prefix("minecraft:").unwrap_or(name).to_string()
+}
+
+// "block/dirt" o "minecraft:block/dirt" -> ("minecraft", "block/dirt")
+fn resource_location(id: &str) -> (&str, &str) {
+    match id.split_once(':') {
+        Some((namespace, path)) => (namespace, path),
+        None => ("minecraft", id),
+    }
+}
+
+fn normalized(id: &str) -> String {
+    let (namespace, path) = resource_location(id);
+    // Los packs viejos omiten la carpeta "block/"
+    if path.contains('/') {
+        format!("{}:{}", namespace, path)
+    } else {
+        format!("{}:block/{}", namespace, path)
+    }
+}
+
+fn asset_path(root: &Path, id: &str, folder: &str, extension: &str) -> PathBuf {
+    let (namespace, path) = resource_location(id);
+    root.join("assets")
+        .join(namespace)
+        .join(folder)
+        .join(format!("{}.{}", path, extension))
+}
+
+fn read_model(root: &Path, id: &str) -> Result<Option<ModelDesc>, ResourcePackError> {
+    let path = asset_path(root, id, "models", "json");
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(BUILTIN_MODELS
+                .iter()
+                .find(|(builtin, _)| *builtin == id)
+                .map(|(_, json)| serde_json::from_str(json).expect("modelo incluido inválido")));
+        }
+        Err(source) => return Err(ResourcePackError::Io { path, source }),
+    };
+    serde_json::from_str(&source)
+        .map(Some)
+        .map_err(|source| ResourcePackError::Parse { path, source })
+}
+
+// Texturas (e identificador de tinte) de las seis caras de un bloque
+fn resolve_block(root: &Path, name: &str) -> Result<Option<FaceTextures>, ResourcePackError> {
+    let tint = biome_tint(name);
+
+    // Seguimos la cadena de padres; los hijos pisan las texturas y los elementos del padre
+    let mut textures: HashMap<String, String> = HashMap::new();
+    let mut elements: Option<Vec<ElementDesc>> = None;
+    let mut next = Some(normalized(&format!("block/{}", name)));
+    let mut found = false;
+    for _ in 0..MAX_MODEL_DEPTH {
+        let Some(id) = next.take() else { break };
+        let Some(model) = read_model(root, &id)? else { break };
+        found = true;
+        for (key, value) in model.textures {
+            textures.entry(key).or_insert(value);
+        }
+        if elements.is_none() {
+            elements = model.elements;
+        }
+        next = model.parent.as_deref().map(normalized);
+    }
+
+    if !found {
+        return Ok(guess_faces(root, name).map(|faces| faces.map(|texture| (texture, None))));
+    }
+
+    // Usamos el primer elemento que ocupa el bloque completo (el resto son capas como
+    // el borde de pasto, que no podemos representar con un cubo)
+    let full_cube = elements.iter().flatten().find(|element| {
+        element.from == [0.0, 0.0, 0.0]
+            && element.to == [16.0, 16.0, 16.0]
+            && FACE_NAMES.iter().all(|face| element.faces.contains_key(*face))
+    });
+    if let Some(element) = full_cube {
+        let mut faces: FaceTextures = Default::default();
+        for (i, face_name) in FACE_NAMES.iter().enumerate() {
+            let face = &element.faces[*face_name];
+            let Some(texture) = resolve_texture(&textures, &face.texture) else {
+                return Ok(None);
+            };
+            faces[i] = (texture, face.tintindex.and(tint));
+        }
+        return Ok(Some(faces));
+    }
+
+    // Bloques sin geometría de cubo (agua, lava): usamos la textura de partículas en todas las caras
+    let particle = textures
+        .get("particle")
+        .and_then(|texture| resolve_texture(&textures, texture));
+    Ok(particle.map(|texture| std::array::from_fn(|_| (texture.clone(), tint))))
+}
+
+// Seguir las referencias "#variable" hasta una textura concreta
+fn resolve_texture(textures: &HashMap<String, String>, reference: &str) -> Option<String> {
+    let mut current = reference;
+    for _ in 0..MAX_MODEL_DEPTH {
+        match current.strip_prefix('#') {
+            Some(variable) => current = textures.get(variable)?,
+            None => return Some(normalized(current)),
+        }
+    }
+    None
+}
+
+// Sin modelo, adivinamos por los nombres de archivo de vanilla: `<bloque>_top`, `_side`, `_end`...
+fn guess_faces(root: &Path, name: &str) -> Option<[String; 6]> {
+    let texture = |suffix: &str| {
+        let id = normalized(&format!("block/{}{}", name, suffix));
+        asset_path(root, &id, "textures", "png").is_file().then_some(id)
+    };
+
+    let side = texture("_side").or_else(|| texture(""))?;
+    let top = texture("_top").or_else(|| texture("_end")).unwrap_or_else(|| side.clone());
+    let bottom = texture("_bottom").or_else(|| texture("_end")).unwrap_or_else(|| top.clone());
+    Some([side.clone(), side.clone(), bottom, top, side.clone(), side])
+}
+
+// Color con el que Minecraft tiñe las caras con `tintindex` de cada bloque
+fn biome_tint(name: &str) -> Option<Color> {
+    match name {
+        "water" | "bubble_column" => Some(WATER_TINT),
+        "spruce_leaves" => Some(SPRUCE_TINT),
+        "birch_leaves" => Some(BIRCH_TINT),
+        _ if name.ends_with("leaves") || name == "vine" => Some(FOLIAGE_TINT),
+        "grass_block" | "short_grass" | "grass" | "tall_grass" | "fern" | "large_fern" | "sugar_cane" => Some(GRASS_TINT),
+        _ => None,
+    }
+}
+
+// Cargar la textura de un bloque; las animadas (tiras verticales de cuadros) se quedan con el primero.
+// Un PNG corrupto no invalida todo el pack: se reemplaza por el tablero magenta y se avisa
+fn load_tile(root: &Path, id: &str, warnings: &mut Vec<String>) -> Option<Texture> {
+    let path = asset_path(root, id, "textures", "png");
+    if !path.is_file() {
+        return None;
+    }
+    let mut img = match open_image(&path) {
+        Ok(img) => img,
+        Err(err) => {
+            warnings.push(format!("{}; se usa un tablero magenta", err));
+            return Some(Texture::placeholder());
+        }
+    };
+    if img.height() > img.width() && img.height() % img.width() == 0 {
+        img = img.crop_imm(0, 0, img.width(), img.width());
+    }
+    Some(Texture::from_image(&img, ColorSpace::Srgb))
+}
+
+// Acomodar las texturas en una cuadrícula de celdas cuadradas del mismo tamaño (potencia de 2).
+// Así cada celda coincide con texels enteros en los mipmaps y los niveles no mezclan bloques vecinos
+fn build_atlas(tiles: &[Texture]) -> (Texture, Vec<TextureRegion>) {
+    let cell = tiles
+        .iter()
+        .map(|tile| tile.width.max(tile.height))
+        .max()
+        .unwrap_or(1)
+        .next_power_of_two();
+    let columns = (tiles.len() as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = tiles.len().div_ceil(columns).max(1);
+    let (width, height) = (columns * cell, rows * cell);
+
+    let mut data = vec![Radiance::BLACK; width * height];
+    let mut alpha = vec![1.0; width * height];
+    let mut regions = Vec::with_capacity(tiles.len());
+    for (i, tile) in tiles.iter().enumerate() {
+        let region = TextureRegion::new((i % columns) * cell, (i / columns) * cell, cell, cell);
+        // Escalado al texel más cercano para los packs que mezclan resoluciones
+        for y in 0..cell {
+            for x in 0..cell {
+                let (tx, ty) = (x * tile.width / cell, y * tile.height / cell);
+                let index = (region.y + y) * width + region.x + x;
+                data[index] = tile.get_pixel(tx, ty);
+                alpha[index] = tile.get_alpha_pixel(tx, ty);
+            }
+        }
+        regions.push(region);
+    }
+
+    let alpha = if tiles.iter().any(Texture::has_alpha) { Some(alpha) } else { None };
+    (Texture::from_pixels(width, height, data, alpha, ColorSpace::Srgb), regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    // Resource pack mínimo en el directorio temporal; se borra al soltarlo
+    struct Pack(PathBuf);
+
+    impl Pack {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("diorama-pack-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("assets/minecraft/models/block")).unwrap();
+            fs::create_dir_all(root.join("assets/minecraft/textures/block")).unwrap();
+            Pack(root)
+        }
+
+        fn model(&self, name: &str, json: &str) -> &Self {
+            fs::write(self.0.join(format!("assets/minecraft/models/block/{}.json", name)), json).unwrap();
+            self
+        }
+
+        fn texture(&self, name: &str, size: (u32, u32), color: [u8; 4]) -> &Self {
+            let path = self.0.join(format!("assets/minecraft/textures/block/{}.png", name));
+            RgbaImage::from_pixel(size.0, size.1, Rgba(color)).save(path).unwrap();
+            self
+        }
+
+        fn load(&self, blocks: &[&str]) -> ResourcePack {
+            ResourcePack::load(&self.0, blocks).unwrap()
+        }
+    }
+
+    impl Drop for Pack {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // Color (sRGB) del texel en la esquina de una cara dentro del atlas
+    fn face_color(pack: &ResourcePack, face: &BlockFace) -> Color {
+        pack.atlas().get_pixel(face.region.x, face.region.y).to_srgb()
+    }
+
+    #[test]
+    fn parent_models_and_variables_are_resolved() {
+        let pack = Pack::new("padres");
+        // `log` hereda la geometría de `column` y éste la de `block/cube` (incluido en el código);
+        // `bottom` apunta a `#top`, que a su vez es una textura concreta
+        pack.model("column", r##"{ "parent": "block/cube", "textures": {
+                "down": "#bottom", "up": "#top", "north": "#side", "south": "#side", "west": "#side", "east": "#side" } }"##)
+            .model("log", r##"{ "parent": "minecraft:block/column", "textures": { "top": "block/log_top", "side": "log", "bottom": "#top" } }"##)
+            .texture("log", (16, 16), [100, 70, 40, 255])
+            .texture("log_top", (16, 16), [180, 150, 100, 255]);
+        let resource_pack = pack.load(&["minecraft:log"]);
+        assert!(resource_pack.missing().is_empty());
+
+        let faces = resource_pack.block_faces("log").unwrap();
+        let side = Color::new(100, 70, 40);
+        let end = Color::new(180, 150, 100);
+        let colors: Vec<Color> = faces.iter().map(|face| face_color(&resource_pack, face)).collect();
+        assert_eq!(colors, vec![side, side, end, end, side, side]);
+        // Las caras con la misma textura comparten región
+        assert_eq!(faces[2].region, faces[3].region);
+        assert_ne!(faces[0].region, faces[3].region);
+    }
+
+    #[test]
+    fn only_faces_with_tintindex_get_the_biome_tint() {
+        let pack = Pack::new("tinte");
+        pack.model("oak_leaves", r#"{ "parent": "block/leaves", "textures": { "all": "block/oak_leaves" } }"#)
+            .model("stone", r#"{ "parent": "block/cube_all", "textures": { "all": "block/stone" } }"#)
+            .texture("oak_leaves", (16, 16), [200, 200, 200, 255])
+            .texture("stone", (16, 16), [120, 120, 120, 255]);
+        let resource_pack = pack.load(&["oak_leaves", "stone"]);
+
+        let leaves = resource_pack.block_faces("oak_leaves").unwrap();
+        assert!(leaves.iter().all(|face| face.tint == Some(FOLIAGE_TINT)));
+        assert!(resource_pack.block_faces("stone").unwrap().iter().all(|face| face.tint.is_none()));
+
+        // El material multiplica la textura por el tinte
+        let base = Material::new(Color::new(255, 255, 255), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0, false, None);
+        let materials = resource_pack.block_materials("oak_leaves", &base).unwrap();
+        let expected = Radiance::from_srgb(Color::new(200, 200, 200)) * Radiance::from_srgb(FOLIAGE_TINT);
+        let color = materials[3].get_diffuse_color(0.5, 0.5);
+        assert!((color.r - expected.r).abs() < 1e-5 && (color.g - expected.g).abs() < 1e-5 && (color.b - expected.b).abs() < 1e-5);
+    }
+
+    #[test]
+    fn atlas_regions_are_power_of_two_cells_without_overlap() {
+        let pack = Pack::new("atlas");
+        // Resoluciones mezcladas y una tira animada de 3 cuadros
+        pack.model("stone", r#"{ "parent": "block/cube_all", "textures": { "all": "block/stone" } }"#)
+            .model("dirt", r#"{ "parent": "block/cube_all", "textures": { "all": "block/dirt" } }"#)
+            .model("magma", r#"{ "parent": "block/cube_all", "textures": { "all": "block/magma" } }"#)
+            .texture("stone", (16, 16), [120, 120, 120, 255])
+            .texture("dirt", (24, 24), [120, 80, 50, 255])
+            .texture("magma", (16, 48), [200, 60, 0, 255]);
+        let resource_pack = pack.load(&["stone", "dirt", "magma"]);
+
+        // Celdas de 32 (la potencia de 2 que cubre 24) en una cuadrícula de 2x2
+        let atlas = resource_pack.atlas();
+        assert_eq!((atlas.width, atlas.height), (64, 64));
+        let regions: Vec<TextureRegion> = ["stone", "dirt", "magma"]
+            .iter()
+            .map(|name| resource_pack.block_faces(name).unwrap()[0].region)
+            .collect();
+        for (i, region) in regions.iter().enumerate() {
+            assert_eq!((region.width, region.height), (32, 32));
+            assert_eq!((region.x % 32, region.y % 32), (0, 0));
+            assert!(region.x + region.width <= atlas.width && region.y + region.height <= atlas.height);
+            assert!(regions[..i].iter().all(|other| other != region));
+        }
+        assert_eq!(face_color(&resource_pack, &resource_pack.block_faces("dirt").unwrap()[0]), Color::new(120, 80, 50));
+
+        // Una UV en la esquina de la cara lee solo su región del atlas
+        let base = Material::new(Color::new(255, 255, 255), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0, false, None);
+        let stone = resource_pack.block_materials("stone", &base).unwrap();
+        assert_eq!(stone[0].get_diffuse_color(0.999, 0.999).to_srgb(), Color::new(120, 120, 120));
+    }
+
+    #[test]
+    fn blocks_without_model_or_textures_are_reported_as_missing() {
+        let pack = Pack::new("faltantes");
+        // Sin modelo, `sandstone` se adivina por los nombres de archivo
+        pack.texture("sandstone", (16, 16), [220, 210, 160, 255])
+            .texture("sandstone_top", (16, 16), [230, 220, 170, 255])
+            .model("broken", r#"{ "parent": "block/cube_all", "textures": { "all": "block/no_such_texture" } }"#);
+        let resource_pack = pack.load(&["sandstone", "broken", "diamond_block"]);
+
+        assert!(resource_pack.block_faces("sandstone").is_some());
+        assert_eq!(resource_pack.missing(), ["diamond_block".to_string(), "broken".to_string()]);
+    }
+
+    #[test]
+    fn zero_width_textures_fall_back_to_the_checkerboard() {
+        let pack = Pack::new("vacia");
+        // PNG con ancho 0 y alto 16
+        let png: [u8; 65] = [
+            137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 0, 0, 0, 0, 16, 8, 6, 0, 0, 0, 56,
+            93, 126, 137, 0, 0, 0, 8, 73, 68, 65, 84, 120, 156, 3, 0, 0, 0, 0, 1, 72, 6, 137, 210, 0, 0, 0, 0, 73, 69,
+            78, 68, 174, 66, 96, 130,
+        ];
+        fs::write(pack.0.join("assets/minecraft/textures/block/empty.png"), png).unwrap();
+        pack.model("empty", r#"{ "parent": "block/cube_all", "textures": { "all": "block/empty" } }"#);
+
+        let resource_pack = pack.load(&["empty"]);
+        assert!(resource_pack.missing().is_empty());
+        assert_eq!(resource_pack.warnings().len(), 1);
+        assert!(resource_pack.warnings()[0].contains("empty.png"));
+        let region = resource_pack.block_faces("empty").unwrap()[0].region;
+        assert_eq!(resource_pack.atlas().get_pixel(region.x, region.y).to_srgb(), Color::new(255, 0, 255));
+    }
+
+    #[test]
+    fn corrupt_textures_fall_back_to_the_checkerboard() {
+        let pack = Pack::new("corrupta");
+        fs::write(pack.0.join("assets/minecraft/textures/block/stone.png"), b"no es un png").unwrap();
+        pack.model("stone", r#"{ "parent": "block/cube_all", "textures": { "all": "block/stone" } }"#);
+
+        let resource_pack = pack.load(&["stone"]);
+        assert!(resource_pack.block_faces("stone").is_some());
+        assert!(resource_pack.warnings()[0].contains("tablero magenta"));
+    }
+
+    #[test]
+    fn folders_without_assets_are_not_resource_packs() {
+        let root = std::env::temp_dir();
+        assert!(matches!(ResourcePack::load(&root, &["stone"]), Err(ResourcePackError::NotAResourcePack { .. })));
+    }
+}
@@ -0,0 +1,92 @@
+use nalgebra::Vector3;
+
+// Generador pseudoaleatorio pequeño (splitmix64). Se siembra con datos del píxel o del punto,
+// así que el resultado no depende de qué hilo renderiza cada bloque
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    state: u64,
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Self {
+        Sampler { state: seed }
+    }
+
+    // Semilla a partir de la posición de un punto en la escena
+    pub fn from_point(point: &Vector3<f32>) -> Self {
+        let seed = point
+            .iter()
+            .fold(0x9e37_79b9_7f4a_7c15, |seed: u64, c| mix(seed ^ c.to_bits() as u64));
+        Sampler::new(seed)
+    }
+
+    // Semilla para una pasada de un píxel: cada pasada del acumulador usa números distintos,
+    // pero repetir el render da el mismo resultado
+    pub fn for_pixel(x: usize, y: usize, pass: u32) -> Self {
+        Sampler::new(mix(mix(mix(x as u64) ^ y as u64) ^ pass as u64))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.state)
+    }
+
+    // Número uniforme en [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // `count` puntos en [0, 1)², uno por celda de una cuadrícula de k x k (k = ⌈√count⌉),
+    // con posición aleatoria dentro de la celda. Devuelve k² puntos
+    pub fn stratified(&mut self, count: u32) -> Vec<(f32, f32)> {
+        let k = (count.max(1) as f32).sqrt().ceil() as u32;
+        let mut points = Vec::with_capacity((k * k) as usize);
+        for i in 0..k {
+            for j in 0..k {
+                let u = (i as f32 + self.next_f32()) / k as f32;
+                let v = (j as f32 + self.next_f32()) / k as f32;
+                points.push((u, v));
+            }
+        }
+        points
+    }
+}
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Llevar un punto del cuadrado unitario al disco unitario conservando las áreas
+// (mapeo concéntrico de Shirley), así la estratificación se mantiene en el disco
+pub fn concentric_disk(u: f32, v: f32) -> (f32, f32) {
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, std::f32::consts::FRAC_PI_4 * (b / a))
+    } else {
+        (b, std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_4 * (a / b))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+// Dirección del hemisferio alrededor de `normal` con densidad proporcional al coseno
+// (el disco concéntrico proyectado hacia arriba)
+pub fn cosine_hemisphere(normal: &Vector3<f32>, u: f32, v: f32) -> Vector3<f32> {
+    let (x, y) = concentric_disk(u, v);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * x + bitangent * y + normal * z).normalize()
+}
+
+// Dos vectores unitarios perpendiculares a `normal` (y entre sí)
+pub fn orthonormal_basis(normal: &Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let helper = if normal.x.abs() > 0.9 { Vector3::y() } else { Vector3::x() };
+    let tangent = normal.cross(&helper).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
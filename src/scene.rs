@@ -0,0 +1,143 @@
+use nalgebra::Vector3;
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::daylight::Daylight;
+use crate::environment::Environment;
+use crate::light::{Light, LightKind};
+use crate::radiance::Radiance;
+use crate::ray_intersect::{Intersect, Material, RayIntersect};
+use crate::texture::Texture;
+
+// Todo lo necesario para renderizar una escena
+pub struct Scene {
+    pub objects: Vec<Box<dyn RayIntersect>>,
+    pub lights: Vec<Light>,
+    pub camera: Camera,
+    pub animated: Vec<usize>, // Índices de los objetos que el visor anima como agua
+    pub materials: HashMap<String, Arc<Material>>, // Registro de materiales compartidos por nombre
+    pub textures: HashMap<String, Arc<Texture>>,   // Registro de texturas compartidas por nombre
+    pub warnings: Vec<String>, // Problemas no fatales al cargar (bloques desconocidos, ...)
+    pub environment: Environment, // Lo que ven los rayos que no chocan con nada
+    pub daylight: Option<Daylight>, // Hora del día: agrega el sol y la luna y colorea el cielo
+    bvh: Option<Bvh>,
+}
+
+impl Scene {
+    pub fn new(objects: Vec<Box<dyn RayIntersect>>, lights: Vec<Light>, camera: Camera) -> Self {
+        Scene {
+            objects,
+            lights,
+            camera,
+            animated: Vec::new(),
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+            warnings: Vec::new(),
+            environment: Environment::default(),
+            daylight: None,
+            bvh: None,
+        }
+    }
+
+    // Agregar un objeto a la escena; invalida la BVH
+    pub fn add_object<T: RayIntersect>(&mut self, object: T) {
+        self.objects.push(Box::new(object));
+        self.bvh = None;
+    }
+
+    // Agregar una luz a la escena
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    // Luces que iluminan el cuadro. Con ciclo de día, el sol y la luna reemplazan a las
+    // luces direccionales de la escena (su "sol" fijo) y las puntuales se conservan
+    pub fn active_lights(&self) -> Vec<Light> {
+        match &self.daylight {
+            Some(daylight) => self
+                .lights
+                .iter()
+                .filter(|light| !matches!(light.kind, LightKind::Directional { .. }))
+                .cloned()
+                .chain(daylight.lights())
+                .collect(),
+            None => self.lights.clone(),
+        }
+    }
+
+    // Dirección hacia el sol: la del ciclo de día o la de la primera luz direccional
+    pub fn sun_direction(&self) -> Option<Vector3<f32>> {
+        match &self.daylight {
+            Some(daylight) => Some(daylight.sun_direction()),
+            None => self.lights.iter().find_map(|light| match light.kind {
+                LightKind::Directional { direction } => Some(direction),
+                LightKind::Point | LightKind::Spot { .. } => None,
+            }),
+        }
+    }
+
+    // Radiancia del entorno para un rayo que escapa de la escena en la dirección unitaria
+    // `direction`. Con ciclo de día, el fondo liso toma el color del cielo según la hora
+    pub fn background(&self, direction: &Vector3<f32>) -> Radiance {
+        match (&self.environment, &self.daylight) {
+            (Environment::Solid(_), Some(daylight)) => daylight.sky_color(),
+            (environment, _) => environment.radiance(direction, self.sun_direction()),
+        }
+    }
+
+    // Construir la BVH. Si se modifica `objects` directamente hay que volver a
+    // llamar a `build_bvh` (o a `refit_bvh` si los objetos solo se movieron).
+    pub fn build_bvh(&mut self) {
+        self.bvh = Some(Bvh::build(&self.objects));
+    }
+
+    // Ajustar las cajas de la BVH después de mover objetos
+    pub fn refit_bvh(&mut self) {
+        if let Some(bvh) = &mut self.bvh {
+            bvh.refit(&self.objects);
+        }
+    }
+
+    // Volver a recorrer los objetos uno por uno
+    pub fn clear_bvh(&mut self) {
+        self.bvh = None;
+    }
+
+    pub fn has_bvh(&self) -> bool {
+        self.bvh.is_some()
+    }
+
+    // Intersección más cercana del rayo con la escena
+    pub fn intersect(&self, ray_origin: &Vector3<f32>, ray_direction: &Vector3<f32>) -> Intersect<'_> {
+        if let Some(bvh) = &self.bvh {
+            return bvh.intersect(&self.objects, ray_origin, ray_direction);
+        }
+
+        let mut closest_intersect = Intersect::empty();
+        let mut zbuffer = f32::INFINITY;
+
+        for object in &self.objects {
+            let intersect = object.ray_intersect(ray_origin, ray_direction);
+            if intersect.is_intersecting && intersect.distance < zbuffer {
+                zbuffer = intersect.distance;
+                closest_intersect = intersect;
+            }
+        }
+
+        closest_intersect
+    }
+
+    // ¿Hay algún objeto entre `ray_origin` y la distancia `max_t` en la dirección (unitaria)
+    // `ray_direction`? Para los rayos de sombra: lo que está más lejos que la luz no cuenta
+    // y el recorrido termina con el primer objeto que tape el rayo
+    pub fn occluded(&self, ray_origin: &Vector3<f32>, ray_direction: &Vector3<f32>, max_t: f32) -> bool {
+        match &self.bvh {
+            Some(bvh) => bvh.occluded(&self.objects, ray_origin, ray_direction, max_t),
+            None => self
+                .objects
+                .iter()
+                .any(|object| object.occludes(ray_origin, ray_direction, max_t)),
+        }
+    }
+}
@@ -0,0 +1,1133 @@
+use nalgebra::Vector3;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::camera::{Camera, Projection};
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::daylight::Daylight;
+use crate::environment::Environment;
+use crate::light::{Attenuation, Light};
+use crate::radiance::Radiance;
+use crate::ray_intersect::{Material, RayIntersect};
+use crate::resource_pack::{ResourcePack, ResourcePackError};
+use crate::scene::Scene;
+use crate::structure::{builtin_block_materials, placeholder_material, with_block_emission, ImportError, Structure};
+use crate::texture::{ColorSpace, Texture, TextureFilter};
+use crate::voxel::{BlockId, VoxelGrid};
+
+// Límite de bloques que puede generar un solo `fill`, para atrapar coordenadas erróneas
+const MAX_FILL_BLOCKS: usize = 1_000_000;
+
+// Máximo de chunks que reservamos para la `VoxelGrid`; bloques más dispersos quedan como cubos
+// sueltos en la BVH en lugar de llenar la memoria con chunks vacíos
+const MAX_GRID_CHUNKS: usize = 1 << 18;
+
+// Intensidad de la luz de un bloque emisivo de fuerza 1, que llega a 15 bloques como en Minecraft
+const BLOCK_LIGHT_INTENSITY: f32 = 1.5;
+const BLOCK_LIGHT_RANGE: f32 = 15.0;
+
+// Errores al cargar un archivo de escena
+#[derive(Debug)]
+pub enum SceneError {
+    Io { path: PathBuf, source: std::io::Error },
+    Parse { path: PathBuf, source: serde_json::Error },
+    UnknownTexture { material: String, texture: String },
+    UnknownMaterial { block: usize, material: String },
+    InvalidBlock { block: usize, reason: String },
+    InvalidValue { field: String, reason: String },
+    ResourcePack(ResourcePackError),
+    Import(ImportError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => {
+                write!(f, "no se pudo leer la escena {}: {}", path.display(), source)
+            }
+            SceneError::Parse { path, source } => {
+                write!(f, "error de sintaxis en {}: {}", path.display(), source)
+            }
+            SceneError::UnknownTexture { material, texture } => {
+                write!(f, "el material \"{}\" usa la textura desconocida \"{}\"", material, texture)
+            }
+            SceneError::UnknownMaterial { block, material } => {
+                write!(f, "el bloque #{} usa el material desconocido \"{}\"", block, material)
+            }
+            SceneError::InvalidBlock { block, reason } => {
+                write!(f, "el bloque #{} es inválido: {}", block, reason)
+            }
+            SceneError::InvalidValue { field, reason } => {
+                write!(f, "valor inválido en {}: {}", field, reason)
+            }
+            SceneError::ResourcePack(err) => write!(f, "resource pack: {}", err),
+            SceneError::Import(err) => write!(f, "importación: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Parse { source, .. } => Some(source),
+            SceneError::ResourcePack(err) => Some(err),
+            SceneError::Import(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    camera: CameraDesc,
+    #[serde(default)]
+    lights: Vec<LightDesc>,
+    #[serde(default)]
+    textures: HashMap<String, TextureDesc>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDesc>,
+    #[serde(default)]
+    blocks: Vec<BlockDesc>,
+    #[serde(default)]
+    structures: Vec<StructureDesc>,
+    #[serde(default)]
+    voxel_grid: bool, // Guardar los bloques unitarios en una `VoxelGrid`
+    #[serde(default)]
+    emissive_lights: bool, // Agregar una luz por cada bloque emisivo
+    background: Option<[u8; 3]>, // Atajo para un entorno de un solo color
+    environment: Option<EnvironmentDesc>,
+    time_of_day: Option<f32>,    // Hora en [0, 24): agrega el sol, la luna y el color del cielo
+    resource_pack: Option<String>, // Carpeta de un resource pack de Minecraft, relativa al archivo
+}
+
+// Entorno que ven los rayos que escapan: `{ "type": "sky", ... }`
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum EnvironmentDesc {
+    Solid {
+        color: [u8; 3],
+    },
+    Gradient {
+        zenith: [u8; 3],
+        horizon: [u8; 3],
+        ground: Option<[u8; 3]>, // Por defecto, el horizonte oscurecido
+    },
+    Sky {
+        #[serde(default = "default_turbidity")]
+        turbidity: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Map {
+        path: String,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+        #[serde(default)]
+        rotation: f32, // En grados, alrededor de Y
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    eye: [f32; 3],
+    center: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+    #[serde(default = "default_fov")]
+    fov: f32, // Campo de visión vertical en grados
+    #[serde(default)]
+    near: f32,
+    #[serde(default)]
+    projection: ProjectionDesc,
+    ortho_height: Option<f32>, // Altura visible con la proyección ortográfica
+    #[serde(default)]
+    aperture: f32, // Radio de la lente
+    focus_distance: Option<f32>, // Por defecto, la distancia a `center`
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ProjectionDesc {
+    #[default]
+    Perspective,
+    Orthographic,
+    Equirectangular,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDesc {
+    position: Option<[f32; 3]>,  // Luz puntual
+    direction: Option<[f32; 3]>, // Luz direccional: hacia dónde está la luz
+    #[serde(default = "default_color")]
+    color: [u8; 3],
+    #[serde(default = "default_intensity")]
+    intensity: f32,
+    #[serde(default)]
+    radius: f32,
+    spot: Option<SpotDesc>, // Convierte la luz puntual en un foco
+    #[serde(default)]
+    attenuation: AttenuationDesc,
+    range: Option<f32>, // Alcance de la atenuación lineal
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpotDesc {
+    direction: [f32; 3],
+    #[serde(default = "default_spot_angle")]
+    angle: f32, // Semiángulo del cono, en grados
+    #[serde(default = "default_spot_softness")]
+    softness: f32, // Grados del borde en que la luz se desvanece
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum AttenuationDesc {
+    #[default]
+    None,
+    InverseSquare,
+    Linear,
+}
+
+// Una textura es una ruta o un objeto `{ "path": ..., "linear": true }` para texturas de datos
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureDesc {
+    Path(String),
+    Detailed {
+        path: String,
+        #[serde(default)]
+        linear: bool,
+    },
+}
+
+impl TextureDesc {
+    fn path(&self) -> &str {
+        match self {
+            TextureDesc::Path(path) | TextureDesc::Detailed { path, .. } => path,
+        }
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        match self {
+            TextureDesc::Detailed { linear: true, .. } => ColorSpace::Linear,
+            _ => ColorSpace::Srgb,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDesc {
+    #[serde(default = "default_color")]
+    diffuse: [u8; 3],
+    texture: Option<String>,
+    #[serde(default = "default_specular")]
+    specular: f32,
+    #[serde(default = "default_albedo")]
+    albedo: [f32; 4],
+    #[serde(default = "default_refractive_index")]
+    refractive_index: f32,
+    #[serde(default)]
+    alpha_cutoff: f32,
+    #[serde(default)]
+    filter: FilterDesc,
+    block: Option<String>, // Bloque de Minecraft cuyas texturas se toman del resource pack
+    #[serde(default)]
+    animated: bool,
+    emission: Option<[u8; 3]>, // Color de la luz que emite
+    #[serde(default = "default_intensity")]
+    emission_strength: f32,
+}
+
+// Material ya construido, con sus caras (distintas solo si vienen de un bloque del resource pack)
+struct LoadedMaterial {
+    material: Arc<Material>,
+    faces: [Arc<Material>; 6],
+    animated: bool,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum FilterDesc {
+    #[default]
+    Nearest,
+    Bilinear,
+    Trilinear,
+}
+
+impl From<FilterDesc> for TextureFilter {
+    fn from(filter: FilterDesc) -> Self {
+        match filter {
+            FilterDesc::Nearest => TextureFilter::Nearest,
+            FilterDesc::Bilinear => TextureFilter::Bilinear,
+            FilterDesc::Trilinear => TextureFilter::Trilinear,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockDesc {
+    material: Option<String>,
+    faces: Option<[String; 6]>,
+    at: Option<[f32; 3]>,
+    positions: Option<Vec<[f32; 3]>>,
+    fill: Option<FillDesc>,
+    #[serde(default = "default_size")]
+    size: f32,
+}
+
+// Estructura importada de un archivo de Minecraft (.schem, .litematic, .nbt o .mca)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructureDesc {
+    path: String,
+    #[serde(default)]
+    at: [f32; 3],          // Centro del bloque (0, 0, 0) de la estructura
+    from: Option<[i32; 3]>, // Zona del mundo a recortar; obligatoria para regiones .mca
+    to: Option<[i32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FillDesc {
+    from: [i32; 3],
+    to: [i32; 3],
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_fov() -> f32 {
+    90.0
+}
+
+fn default_color() -> [u8; 3] {
+    [255, 255, 255]
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_spot_angle() -> f32 {
+    30.0
+}
+
+fn default_spot_softness() -> f32 {
+    5.0
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+fn default_specular() -> f32 {
+    32.0
+}
+
+fn default_albedo() -> [f32; 4] {
+    [1.0, 0.1, 0.0, 0.0]
+}
+
+fn default_refractive_index() -> f32 {
+    1.0
+}
+
+fn default_size() -> f32 {
+    1.0
+}
+
+// Cargar una escena desde un archivo JSON; las rutas de las texturas son relativas al archivo
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    load_scene_from(path.as_ref(), None)
+}
+
+// Igual que `load_scene`, pero tomando las texturas de los bloques de otro resource pack
+// en lugar del que indica el archivo
+pub fn load_scene_with_resource_pack<P: AsRef<Path>, Q: AsRef<Path>>(path: P, resource_pack: Q) -> Result<Scene, SceneError> {
+    load_scene_from(path.as_ref(), Some(resource_pack.as_ref()))
+}
+
+fn load_scene_from(path: &Path, resource_pack: Option<&Path>) -> Result<Scene, SceneError> {
+    let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let desc: SceneDesc = serde_json::from_str(&source).map_err(|source| SceneError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    build_scene(desc, base_dir, resource_pack)
+}
+
+// Cargar una escena desde un texto JSON ya leído
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let desc: SceneDesc = serde_json::from_str(source).map_err(|source| SceneError::Parse {
+        path: PathBuf::from("<memoria>"),
+        source,
+    })?;
+
+    build_scene(desc, base_dir, None)
+}
+
+fn build_scene(desc: SceneDesc, base_dir: &Path, resource_pack: Option<&Path>) -> Result<Scene, SceneError> {
+    let camera = build_camera(&desc.camera)?;
+
+    let mut lights = Vec::with_capacity(desc.lights.len());
+    for (i, light) in desc.lights.iter().enumerate() {
+        lights.push(match (&light.position, &light.direction) {
+            (Some(position), None) => {
+                let position = vector(position, &format!("lights[{}].position", i))?;
+                let built = match &light.spot {
+                    Some(spot) => {
+                        let direction = vector(&spot.direction, &format!("lights[{}].spot.direction", i))?;
+                        if direction.magnitude() == 0.0 {
+                            return Err(SceneError::InvalidValue {
+                                field: format!("lights[{}].spot.direction", i),
+                                reason: "la dirección no puede ser cero".to_string(),
+                            });
+                        }
+                        if !(spot.angle > 0.0 && spot.angle < 90.0) || spot.softness < 0.0 {
+                            return Err(SceneError::InvalidValue {
+                                field: format!("lights[{}].spot", i),
+                                reason: format!(
+                                    "el ángulo debe estar entre 0 y 90 grados y el borde no puede ser negativo ({} y {})",
+                                    spot.angle, spot.softness
+                                ),
+                            });
+                        }
+                        Light::spot(
+                            position,
+                            direction,
+                            color(light.color),
+                            light.intensity,
+                            light.radius,
+                            (spot.angle - spot.softness).max(0.0).to_radians(),
+                            spot.angle.to_radians(),
+                        )
+                    }
+                    None => Light::new(position, color(light.color), light.intensity, light.radius),
+                };
+                built.with_attenuation(attenuation(i, light)?)
+            }
+            (None, Some(_)) if light.spot.is_some() => {
+                return Err(SceneError::InvalidValue {
+                    field: format!("lights[{}].spot", i),
+                    reason: "un foco necesita \"position\", no \"direction\"".to_string(),
+                })
+            }
+            (None, Some(direction)) => {
+                let direction = vector(direction, &format!("lights[{}].direction", i))?;
+                if direction.magnitude() == 0.0 {
+                    return Err(SceneError::InvalidValue {
+                        field: format!("lights[{}].direction", i),
+                        reason: "la dirección no puede ser cero".to_string(),
+                    });
+                }
+                Light::directional(direction, color(light.color), light.intensity, light.radius)
+            }
+            _ => {
+                return Err(SceneError::InvalidValue {
+                    field: format!("lights[{}]", i),
+                    reason: "cada luz necesita \"position\" o \"direction\" (solo uno)".to_string(),
+                })
+            }
+        });
+    }
+    let daylight = match desc.time_of_day {
+        Some(time) if !time.is_finite() => {
+            return Err(SceneError::InvalidValue {
+                field: "time_of_day".to_string(),
+                reason: format!("hora inválida {}", time),
+            })
+        }
+        Some(time) => Some(Daylight::new(time)),
+        None => None,
+    };
+
+    // Solo cargamos las texturas que algún material usa; cada una se carga una vez
+    // y todos los materiales que la usan la comparten
+    let mut textures: HashMap<&str, Arc<Texture>> = HashMap::new();
+    let mut materials: HashMap<&str, LoadedMaterial> = HashMap::new();
+    let mut warnings = Vec::new();
+
+    // Con resource pack, los materiales con `block` toman las caras del bloque del pack;
+    // los bloques que el pack no tiene se quedan con la definición del material
+    let pack_dir = match resource_pack {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => desc.resource_pack.as_ref().map(|dir| base_dir.join(dir)),
+    };
+    let structures = desc
+        .structures
+        .iter()
+        .enumerate()
+        .map(|(i, structure)| load_structure(i, structure, base_dir))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let pack = match pack_dir {
+        Some(dir) => {
+            let mut blocks: Vec<&str> = desc.materials.values().filter_map(|m| m.block.as_deref()).collect();
+            for structure in &structures {
+                blocks.extend(structure.palette().iter().filter(|s| !s.is_air()).map(|s| s.short_name()));
+            }
+            Some(ResourcePack::load(dir, &blocks).map_err(SceneError::ResourcePack)?)
+        }
+        None => None,
+    };
+    for (name, material) in &desc.materials {
+        // Con 1.0 o más hasta los texels opacos se descartarían
+        if !(0.0..1.0).contains(&material.alpha_cutoff) {
+            return Err(SceneError::InvalidValue {
+                field: format!("materials.{}.alpha_cutoff", name),
+                reason: format!("debe estar en [0, 1), se recibió {}", material.alpha_cutoff),
+            });
+        }
+
+        let texture = match &material.texture {
+            Some(texture_name) => {
+                let file = desc.textures.get(texture_name).ok_or_else(|| SceneError::UnknownTexture {
+                    material: name.clone(),
+                    texture: texture_name.clone(),
+                })?;
+                // Una textura que falta o está corrupta no detiene la carga: se dibuja
+                // como tablero magenta y se avisa
+                let texture = textures.entry(texture_name.as_str()).or_insert_with(|| {
+                    let path = base_dir.join(file.path());
+                    match Texture::load_with_color_space(&path, file.color_space()) {
+                        Ok(texture) => Arc::new(texture),
+                        Err(err) => {
+                            warnings.push(format!("{}; se usa un tablero magenta", err));
+                            Arc::new(Texture::placeholder())
+                        }
+                    }
+                });
+                Some(texture.clone())
+            }
+            None => None,
+        };
+
+        let mut built = Material::new(
+            color(material.diffuse),
+            material.specular,
+            material.albedo,
+            material.refractive_index,
+            texture.is_some(),
+            texture,
+        )
+        .with_alpha_cutoff(material.alpha_cutoff)
+        .with_filter(material.filter.into());
+        if let Some(emission) = material.emission {
+            built = built.with_emission(color(emission), material.emission_strength);
+        }
+        let faces = pack
+            .as_ref()
+            .zip(material.block.as_deref())
+            .and_then(|(pack, block)| pack.block_materials(block, &built));
+        let built = Arc::new(built);
+        materials.insert(
+            name.as_str(),
+            LoadedMaterial {
+                faces: faces.unwrap_or_else(|| std::array::from_fn(|_| Arc::clone(&built))),
+                material: built,
+                animated: material.animated,
+            },
+        );
+    }
+
+    let mut scene = Scene::new(Vec::new(), lights, camera);
+    scene.warnings = warnings;
+    scene.daylight = daylight;
+    if let Some(environment) = build_environment(&desc, base_dir, &mut scene.warnings)? {
+        scene.environment = environment;
+    }
+    for (name, texture) in &textures {
+        scene.textures.insert(name.to_string(), Arc::clone(texture));
+    }
+    if let Some(pack) = &pack {
+        scene.textures.insert("resource_pack".to_string(), Arc::clone(pack.atlas()));
+    }
+    for (name, loaded) in &materials {
+        scene.materials.insert(name.to_string(), Arc::clone(&loaded.material));
+    }
+    let mut grid_blocks: Vec<([i32; 3], usize)> = Vec::new();
+    let mut grid_types: Vec<[Arc<Material>; 6]> = Vec::new();
+    let mut block_lights = Vec::new();
+    for (index, block) in desc.blocks.iter().enumerate() {
+        let (faces, animated) = block_materials(index, block, &materials)?;
+        let faces = if desc.emissive_lights && !animated { block_light_faces(faces) } else { faces };
+        if !(block.size.is_finite() && block.size > 0.0) {
+            return Err(SceneError::InvalidBlock {
+                block: index,
+                reason: format!("el tamaño debe ser positivo, no {}", block.size),
+            });
+        }
+
+        // Los bloques unitarios en posiciones enteras van a la cuadrícula de vóxeles;
+        // los animados siempre son cubos sueltos para que el visor pueda moverlos
+        let grid_type = grid_types.len();
+        for center in block_positions(index, block)? {
+            if desc.voxel_grid && !animated && block.size == 1.0 {
+                if let Some(cell) = integer_cell(&center) {
+                    grid_blocks.push((cell, grid_type));
+                    continue;
+                }
+            }
+
+            if animated {
+                scene.animated.push(scene.objects.len());
+            } else if desc.emissive_lights {
+                block_lights.extend(block_light(&faces, center, block.size));
+            }
+            let cube: Box<dyn RayIntersect> = Box::new(Cube::new(center, block.size, faces.clone()));
+            scene.objects.push(cube);
+        }
+        if grid_blocks.last().is_some_and(|&(_, t)| t == grid_type) {
+            grid_types.push(faces);
+        }
+    }
+
+    if !grid_blocks.is_empty() {
+        let (min, dims) = grid_bounds(&grid_blocks);
+        if VoxelGrid::chunk_count(dims).is_some_and(|count| count <= MAX_GRID_CHUNKS) {
+            let grid = build_voxel_grid(&grid_blocks, min, dims, grid_types)?;
+            if desc.emissive_lights {
+                block_lights.extend(grid_lights(&grid));
+            }
+            scene.objects.push(Box::new(grid));
+        } else {
+            for (cell, block_type) in &grid_blocks {
+                let center = Vector3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32);
+                if desc.emissive_lights {
+                    block_lights.extend(block_light(&grid_types[*block_type], center, 1.0));
+                }
+                scene.objects.push(Box::new(Cube::new(center, 1.0, grid_types[*block_type].clone())));
+            }
+        }
+    }
+
+    // Los bloques de las estructuras usan, en orden: el material de la escena con ese `block`,
+    // el bloque del resource pack, un color aproximado, o un material magenta que se reporta.
+    // Los del pack y los de color emiten luz si el bloque la emite en Minecraft
+    let by_block: HashMap<&str, &LoadedMaterial> = desc
+        .materials
+        .iter()
+        .filter_map(|(name, m)| {
+            let block = m.block.as_deref()?;
+            Some((block.strip_prefix("minecraft:").unwrap_or(block), &materials[name.as_str()]))
+        })
+        .collect();
+    let base = Material::new(color(default_color()), default_specular(), default_albedo(), 1.0, false, None);
+    let placeholder = Arc::new(placeholder_material());
+    for (structure_desc, structure) in desc.structures.iter().zip(&structures) {
+        let at = vector(&structure_desc.at, "structures.at")?;
+        let (grid, unknown) = structure.to_voxel_grid(
+            at,
+            |state| {
+                let name = state.short_name();
+                by_block
+                    .get(name)
+                    .map(|loaded| loaded.faces.clone())
+                    .or_else(|| {
+                        pack.as_ref()
+                            .and_then(|pack| pack.block_materials(name, &base))
+                            .or_else(|| builtin_block_materials(name))
+                            .map(|faces| with_block_emission(name, faces))
+                    })
+                    .map(|faces| if desc.emissive_lights { block_light_faces(faces) } else { faces })
+            },
+            &placeholder,
+        );
+        for name in unknown {
+            scene.warnings.push(format!(
+                "{}: bloque desconocido {}, se dibuja en magenta",
+                structure_desc.path, name
+            ));
+        }
+        if desc.emissive_lights {
+            block_lights.extend(grid_lights(&grid));
+        }
+        scene.objects.push(Box::new(grid));
+    }
+    scene.lights.extend(block_lights);
+    if let Some(pack) = &pack {
+        scene.warnings.extend(pack.warnings().iter().cloned());
+        for name in pack.missing() {
+            scene.warnings.push(format!("el resource pack no tiene el bloque {}", name));
+        }
+    }
+
+    scene.build_bvh();
+    Ok(scene)
+}
+
+fn build_camera(desc: &CameraDesc) -> Result<Camera, SceneError> {
+    let invalid = |field: &str, reason: String| SceneError::InvalidValue { field: format!("camera.{}", field), reason };
+    if !(desc.fov > 0.0 && desc.fov < 180.0) {
+        return Err(invalid("fov", format!("debe estar entre 0 y 180 grados, se recibió {}", desc.fov)));
+    }
+    if !(desc.near >= 0.0 && desc.near.is_finite()) {
+        return Err(invalid("near", format!("debe ser 0 o positivo, no {}", desc.near)));
+    }
+    if !(desc.aperture >= 0.0 && desc.aperture.is_finite()) {
+        return Err(invalid("aperture", format!("debe ser 0 o positiva, no {}", desc.aperture)));
+    }
+    if desc.aperture > 0.0 && desc.projection == ProjectionDesc::Equirectangular {
+        return Err(invalid("aperture", "la proyección equirectangular no tiene lente".to_string()));
+    }
+
+    let camera = Camera::new(
+        vector(&desc.eye, "camera.eye")?,
+        vector(&desc.center, "camera.center")?,
+        vector(&desc.up, "camera.up")?,
+    )
+    .with_fov(desc.fov.to_radians())
+    .with_near(desc.near)
+    .with_aperture(desc.aperture);
+    let camera = match desc.focus_distance {
+        Some(distance) if distance > 0.0 && distance.is_finite() => camera.with_focus_distance(distance),
+        Some(distance) => return Err(invalid("focus_distance", format!("debe ser positiva, no {}", distance))),
+        None => camera,
+    };
+    let projection = match (desc.projection, desc.ortho_height) {
+        (ProjectionDesc::Orthographic, Some(height)) if height > 0.0 && height.is_finite() => Projection::Orthographic { height },
+        (ProjectionDesc::Orthographic, Some(height)) => {
+            return Err(invalid("ortho_height", format!("debe ser positiva, no {}", height)))
+        }
+        (ProjectionDesc::Orthographic, None) => Projection::Orthographic { height: camera.framing_height() },
+        (_, Some(_)) => return Err(invalid("ortho_height", "solo se usa con \"projection\": \"orthographic\"".to_string())),
+        (ProjectionDesc::Perspective, None) => Projection::Perspective,
+        (ProjectionDesc::Equirectangular, None) => Projection::Equirectangular,
+    };
+    Ok(camera.with_projection(projection))
+}
+
+fn attenuation(index: usize, light: &LightDesc) -> Result<Attenuation, SceneError> {
+    match (light.attenuation, light.range) {
+        (AttenuationDesc::Linear, Some(range)) if range > 0.0 => Ok(Attenuation::Linear { range }),
+        (AttenuationDesc::Linear, Some(range)) => Err(SceneError::InvalidValue {
+            field: format!("lights[{}].range", index),
+            reason: format!("el alcance debe ser positivo, no {}", range),
+        }),
+        (AttenuationDesc::Linear, None) => Ok(Attenuation::Linear { range: BLOCK_LIGHT_RANGE }),
+        (_, Some(_)) => Err(SceneError::InvalidValue {
+            field: format!("lights[{}].range", index),
+            reason: "solo se usa con \"attenuation\": \"linear\"".to_string(),
+        }),
+        (AttenuationDesc::None, None) => Ok(Attenuation::None),
+        (AttenuationDesc::InverseSquare, None) => Ok(Attenuation::InverseSquare),
+    }
+}
+
+// Luz de área para un bloque emisivo: una esfera que envuelve el bloque (así su propio bloque
+// no le hace sombra), del color de la emisión y con el alcance de la luz de Minecraft
+fn block_light(faces: &[Arc<Material>; 6], center: Vector3<f32>, size: f32) -> Option<Light> {
+    let material = faces.iter().find(|face| face.is_emissive())?;
+    let strength = material.emission_strength;
+    let light = Light::new(center, material.emission, strength * BLOCK_LIGHT_INTENSITY, size * 3f32.sqrt() / 2.0);
+    Some(light.with_attenuation(Attenuation::Linear { range: BLOCK_LIGHT_RANGE * strength.min(1.0) * size }))
+}
+
+// Las caras emisivas de un bloque que tendrá luz, marcadas para que el path tracer no sume
+// su emisión dos veces; se copian porque un bloque animado puede usar el mismo material
+fn block_light_faces(faces: [Arc<Material>; 6]) -> [Arc<Material>; 6] {
+    faces.map(|face| if face.is_emissive() { Arc::new((*face).clone().with_block_light()) } else { face })
+}
+
+// Una luz por cada bloque emisivo de la cuadrícula
+fn grid_lights(grid: &VoxelGrid) -> Vec<Light> {
+    let half = Vector3::repeat(grid.block_size / 2.0);
+    grid.blocks()
+        .filter_map(|(cell, id)| {
+            let faces = grid.block_materials(id)?;
+            block_light(faces, grid.cell_min(cell) + half, grid.block_size)
+        })
+        .collect()
+}
+
+fn build_environment(desc: &SceneDesc, base_dir: &Path, warnings: &mut Vec<String>) -> Result<Option<Environment>, SceneError> {
+    let environment = match (&desc.background, &desc.environment) {
+        (Some(_), Some(_)) => {
+            return Err(SceneError::InvalidValue {
+                field: "background".to_string(),
+                reason: "no se puede usar junto con \"environment\"".to_string(),
+            })
+        }
+        (Some(background), None) => Environment::Solid(Radiance::from_srgb(color(*background))),
+        (None, Some(EnvironmentDesc::Solid { color: background })) => Environment::Solid(Radiance::from_srgb(color(*background))),
+        (None, Some(EnvironmentDesc::Gradient { zenith, horizon, ground })) => {
+            let horizon = Radiance::from_srgb(color(*horizon));
+            Environment::Gradient {
+                zenith: Radiance::from_srgb(color(*zenith)),
+                horizon,
+                ground: ground.map(|ground| Radiance::from_srgb(color(ground))).unwrap_or(horizon.scale(0.3)),
+            }
+        }
+        (None, Some(EnvironmentDesc::Sky { turbidity, intensity })) => {
+            // Fuera de este rango el ajuste de Preetham da colores sin sentido
+            if !(1.7..=10.0).contains(turbidity) {
+                return Err(SceneError::InvalidValue {
+                    field: "environment.turbidity".to_string(),
+                    reason: format!("debe estar entre 1.7 y 10, se recibió {}", turbidity),
+                });
+            }
+            Environment::Sky { turbidity: *turbidity, intensity: *intensity }
+        }
+        (None, Some(EnvironmentDesc::Map { path, intensity, rotation })) => {
+            let texture = match Texture::load_from_file(base_dir.join(path)) {
+                Ok(texture) => texture,
+                Err(err) => {
+                    warnings.push(format!("{}; se usa un tablero magenta", err));
+                    Texture::placeholder()
+                }
+            };
+            Environment::Map {
+                texture: Arc::new(texture),
+                intensity: *intensity,
+                rotation: rotation.to_radians(),
+            }
+        }
+        (None, None) => return Ok(None),
+    };
+    Ok(Some(environment))
+}
+
+fn load_structure(index: usize, desc: &StructureDesc, base_dir: &Path) -> Result<Structure, SceneError> {
+    let path = base_dir.join(&desc.path);
+    let is_region = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("mca"));
+    match (desc.from, desc.to) {
+        (Some(from), Some(to)) if is_region => Structure::load_region(&path, from, to),
+        _ if is_region => {
+            return Err(SceneError::InvalidValue {
+                field: format!("structures[{}]", index),
+                reason: "las regiones .mca necesitan \"from\" y \"to\"".to_string(),
+            })
+        }
+        (None, None) => Structure::load(&path),
+        _ => {
+            return Err(SceneError::InvalidValue {
+                field: format!("structures[{}]", index),
+                reason: "\"from\" y \"to\" solo se usan con regiones .mca".to_string(),
+            })
+        }
+    }
+    .map_err(SceneError::Import)
+}
+
+// Centro entero de un bloque unitario, si lo tiene
+fn integer_cell(center: &Vector3<f32>) -> Option<[i32; 3]> {
+    let cell = [center.x.round(), center.y.round(), center.z.round()];
+    let aligned = (0..3).all(|axis| (center[axis] - cell[axis]).abs() < 1e-4);
+    let in_range = cell.iter().all(|c| c.abs() < i32::MAX as f32);
+    (aligned && in_range).then(|| cell.map(|c| c as i32))
+}
+
+// Esquina mínima y tamaño en bloques de la caja envolvente de los bloques alineados
+fn grid_bounds(blocks: &[([i32; 3], usize)]) -> ([i32; 3], [usize; 3]) {
+    let mut min = [i32::MAX; 3];
+    let mut max = [i32::MIN; 3];
+    for (cell, _) in blocks {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(cell[axis]);
+            max[axis] = max[axis].max(cell[axis]);
+        }
+    }
+    (min, [0, 1, 2].map(|axis| (max[axis] as i64 - min[axis] as i64 + 1) as usize))
+}
+
+// Agrupar los bloques alineados en una `VoxelGrid` que cubre su caja envolvente
+fn build_voxel_grid(
+    blocks: &[([i32; 3], usize)],
+    min: [i32; 3],
+    dims: [usize; 3],
+    types: Vec<[Arc<Material>; 6]>,
+) -> Result<VoxelGrid, SceneError> {
+    if types.len() > BlockId::MAX as usize {
+        return Err(SceneError::InvalidValue {
+            field: "voxel_grid".to_string(),
+            reason: format!("demasiados tipos de bloque ({}, máximo {})", types.len(), BlockId::MAX),
+        });
+    }
+
+    // Cada celda va de (centro - 0.5) a (centro + 0.5)
+    let origin = Vector3::new(min[0] as f32 - 0.5, min[1] as f32 - 0.5, min[2] as f32 - 0.5);
+    let mut grid = VoxelGrid::new(origin, dims, 1.0);
+    let ids: Vec<BlockId> = types.into_iter().map(|faces| grid.add_block_type(faces)).collect();
+    for (cell, block_type) in blocks {
+        grid.set(
+            (cell[0] - min[0]) as usize,
+            (cell[1] - min[1]) as usize,
+            (cell[2] - min[2]) as usize,
+            ids[*block_type],
+        );
+    }
+    Ok(grid)
+}
+
+// Resolver los materiales de las seis caras de un bloque
+fn block_materials(
+    index: usize,
+    block: &BlockDesc,
+    materials: &HashMap<&str, LoadedMaterial>,
+) -> Result<([Arc<Material>; 6], bool), SceneError> {
+    let lookup = |name: &str| {
+        materials.get(name).ok_or_else(|| SceneError::UnknownMaterial {
+            block: index,
+            material: name.to_string(),
+        })
+    };
+
+    match (&block.material, &block.faces) {
+        (Some(name), None) => {
+            let loaded = lookup(name)?;
+            Ok((loaded.faces.clone(), loaded.animated))
+        }
+        (None, Some(names)) => {
+            for name in names {
+                lookup(name)?;
+            }
+            // Cada cara toma la cara correspondiente de su material (importa con resource pack)
+            let faces = std::array::from_fn(|i| Arc::clone(&materials[names[i].as_str()].faces[i]));
+            let animated = names.iter().any(|name| materials[name.as_str()].animated);
+            Ok((faces, animated))
+        }
+        _ => Err(SceneError::InvalidBlock {
+            block: index,
+            reason: "se debe indicar exactamente uno de \"material\" o \"faces\"".to_string(),
+        }),
+    }
+}
+
+// Calcular los centros de los cubos de un bloque (`at`, `positions` o `fill`)
+fn block_positions(index: usize, block: &BlockDesc) -> Result<Vec<Vector3<f32>>, SceneError> {
+    let field = |name: &str| format!("blocks[{}].{}", index, name);
+
+    match (&block.at, &block.positions, &block.fill) {
+        (Some(at), None, None) => Ok(vec![vector(at, &field("at"))?]),
+        (None, Some(positions), None) => positions
+            .iter()
+            .enumerate()
+            .map(|(i, p)| vector(p, &field(&format!("positions[{}]", i))))
+            .collect(),
+        (None, None, Some(fill)) => {
+            let mut count: usize = 1;
+            for axis in 0..3 {
+                if fill.from[axis] > fill.to[axis] {
+                    return Err(SceneError::InvalidBlock {
+                        block: index,
+                        reason: format!(
+                            "\"fill.from\" {:?} es mayor que \"fill.to\" {:?} en el eje {}",
+                            fill.from,
+                            fill.to,
+                            ["x", "y", "z"][axis]
+                        ),
+                    });
+                }
+                let extent = (fill.to[axis] as i64 - fill.from[axis] as i64 + 1) as usize;
+                count = count.saturating_mul(extent);
+            }
+            if count > MAX_FILL_BLOCKS {
+                return Err(SceneError::InvalidBlock {
+                    block: index,
+                    reason: format!("\"fill\" genera {} bloques (máximo {})", count, MAX_FILL_BLOCKS),
+                });
+            }
+
+            // Recorremos X dentro de Z dentro de Y, igual que la cuadrícula original
+            let mut centers = Vec::with_capacity(count);
+            for y in fill.from[1]..=fill.to[1] {
+                for z in fill.from[2]..=fill.to[2] {
+                    for x in fill.from[0]..=fill.to[0] {
+                        centers.push(Vector3::new(x as f32, y as f32, z as f32) * block.size);
+                    }
+                }
+            }
+            Ok(centers)
+        }
+        _ => Err(SceneError::InvalidBlock {
+            block: index,
+            reason: "se debe indicar exactamente uno de \"at\", \"positions\" o \"fill\"".to_string(),
+        }),
+    }
+}
+
+fn vector(values: &[f32; 3], field: &str) -> Result<Vector3<f32>, SceneError> {
+    if values.iter().all(|v| v.is_finite()) {
+        Ok(Vector3::new(values[0], values[1], values[2]))
+    } else {
+        Err(SceneError::InvalidValue {
+            field: field.to_string(),
+            reason: format!("coordenadas no finitas {:?}", values),
+        })
+    }
+}
+
+fn color(values: [u8; 3]) -> Color {
+    Color::new(values[0], values[1], values[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Escena mínima con los materiales, texturas y bloques indicados
+    fn scene(materials: &str, textures: &str, blocks: &str) -> String {
+        format!(
+            r#"{{
+                "camera": {{ "eye": [0, 0, -5], "center": [0, 0, 0] }},
+                "textures": {{ {} }},
+                "materials": {{ {} }},
+                "blocks": [ {} ]
+            }}"#,
+            textures, materials, blocks
+        )
+    }
+
+    fn parse(source: &str) -> Result<Scene, SceneError> {
+        parse_scene(source, Path::new("."))
+    }
+
+    #[test]
+    fn valid_scene_is_loaded() {
+        let source = scene(r#""piedra": { "diffuse": [128, 128, 128] }"#, "", r#"{ "material": "piedra", "at": [0, 0, 0] }"#);
+        let scene = parse(&source).unwrap();
+        assert_eq!(scene.objects.len(), 1);
+    }
+
+    #[test]
+    fn unknown_material_reports_the_block() {
+        let source = scene(
+            r#""piedra": { "diffuse": [128, 128, 128] }"#,
+            "",
+            r#"{ "material": "piedra", "at": [0, 0, 0] }, { "material": "granito", "at": [1, 0, 0] }"#,
+        );
+        match parse(&source) {
+            Err(SceneError::UnknownMaterial { block, material }) => {
+                assert_eq!(block, 1);
+                assert_eq!(material, "granito");
+            }
+            other => panic!("se esperaba UnknownMaterial, no {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn unknown_texture_reports_the_material() {
+        let source = scene(r#""piedra": { "texture": "roca" }"#, "", r#"{ "material": "piedra", "at": [0, 0, 0] }"#);
+        match parse(&source) {
+            Err(SceneError::UnknownTexture { material, texture }) => {
+                assert_eq!(material, "piedra");
+                assert_eq!(texture, "roca");
+            }
+            other => panic!("se esperaba UnknownTexture, no {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn missing_or_corrupt_texture_files_become_a_placeholder_with_a_warning() {
+        let dir = std::env::temp_dir().join(format!("diorama-scene-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("rota.png"), b"esto no es un png").unwrap();
+        let source = scene(
+            r#""piedra": { "texture": "falta" }, "vidrio": { "texture": "rota" }"#,
+            r#""falta": "no-existe.png", "rota": "rota.png""#,
+            r#"{ "material": "piedra", "at": [0, 0, 0] }, { "material": "vidrio", "at": [1, 0, 0] }"#,
+        );
+        let result = parse_scene(&source, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let scene = result.unwrap();
+        assert_eq!(scene.objects.len(), 2);
+        assert_eq!(scene.warnings.len(), 2, "{:?}", scene.warnings);
+        assert!(scene.warnings.iter().any(|w| w.contains("no-existe.png")));
+        assert!(scene.warnings.iter().any(|w| w.contains("rota.png")));
+        assert!(scene.warnings.iter().all(|w| w.contains("tablero magenta")));
+    }
+
+    #[test]
+    fn malformed_json_is_a_parse_error() {
+        assert!(matches!(parse(r#"{ "camera": { "eye": [0, 0, -5], "center": [0, 0, 0] }"#), Err(SceneError::Parse { .. })));
+        assert!(matches!(parse("[1, 2, 3]"), Err(SceneError::Parse { .. })));
+        // Los campos mal escritos también se reportan
+        let misspelled = scene(r#""piedra": { "difuse": [128, 128, 128] }"#, "", "");
+        match parse(&misspelled) {
+            Err(SceneError::Parse { source, .. }) => assert!(source.to_string().contains("difuse")),
+            other => panic!("se esperaba Parse, no {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn missing_scene_file_is_an_io_error() {
+        assert!(matches!(load_scene("no-existe/escena.json"), Err(SceneError::Io { .. })));
+    }
+
+    #[test]
+    fn sparse_blocks_fall_back_to_loose_cubes() {
+        let grid = |far: i32| {
+            scene(
+                r#""piedra": { "diffuse": [128, 128, 128] }"#,
+                "",
+                &format!(r#"{{ "material": "piedra", "positions": [[0, 0, 0], [1, 0, 0], [{}, 0, 0]] }}"#, far),
+            )
+            .replace(r#""camera""#, r#""voxel_grid": true, "camera""#)
+        };
+        // Cerca: una sola cuadrícula con los tres bloques
+        let mut scene = parse(&grid(40)).unwrap();
+        assert_eq!(scene.objects.len(), 1);
+        assert!(scene.objects[0].as_any_mut().is::<VoxelGrid>());
+        // Una cuadrícula de dos mil millones de bloques de largo sería casi toda aire
+        let mut scene = parse(&grid(2_000_000_000)).unwrap();
+        assert_eq!(scene.objects.len(), 3);
+        assert!(scene.objects.iter_mut().all(|o| o.as_any_mut().is::<Cube>()));
+    }
+
+    #[test]
+    fn invalid_values_report_the_field() {
+        let source = scene(r#""piedra": { "diffuse": [128, 128, 128] }"#, "", r#"{ "material": "piedra", "at": [0, 0, 0], "size": -1 }"#);
+        assert!(matches!(parse(&source), Err(SceneError::InvalidBlock { block: 0, .. })));
+
+        let source = scene(r#""piedra": { "diffuse": [128, 128, 128] }"#, "", r#"{ "material": "piedra", "fill": { "from": [2, 0, 0], "to": [0, 0, 0] } }"#);
+        assert!(matches!(parse(&source), Err(SceneError::InvalidBlock { block: 0, .. })));
+
+        // 1e39 no cabe en un f32 y se lee como infinito
+        let source = scene("", "", "").replace(r#""eye": [0, 0, -5]"#, r#""eye": [1e39, 0, -5]"#);
+        match parse(&source) {
+            Err(SceneError::InvalidValue { field, .. }) => assert_eq!(field, "camera.eye"),
+            other => panic!("se esperaba InvalidValue, no {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn camera_options_are_validated() {
+        let camera = |options: &str| scene("", "", "").replace(r#""center": [0, 0, 0]"#, &format!(r#""center": [0, 0, 0], {}"#, options));
+        let field = |options: &str| match parse(&camera(options)) {
+            Err(SceneError::InvalidValue { field, .. }) => field,
+            other => panic!("se esperaba InvalidValue, no {:?}", other.err()),
+        };
+        assert_eq!(field(r#""fov": 180"#), "camera.fov");
+        assert_eq!(field(r#""fov": 0"#), "camera.fov");
+        assert_eq!(field(r#""near": -1"#), "camera.near");
+        assert_eq!(field(r#""ortho_height": 10"#), "camera.ortho_height");
+        assert_eq!(field(r#""projection": "orthographic", "ortho_height": 0"#), "camera.ortho_height");
+        assert_eq!(field(r#""aperture": -0.1"#), "camera.aperture");
+        assert_eq!(field(r#""aperture": 0.1, "projection": "equirectangular""#), "camera.aperture");
+        assert_eq!(field(r#""focus_distance": 0"#), "camera.focus_distance");
+
+        let scene = parse(&camera(r#""fov": 60, "near": 0.5, "projection": "orthographic", "ortho_height": 8"#)).unwrap();
+        assert!((scene.camera.fov - 60f32.to_radians()).abs() < 1e-6);
+        assert_eq!(scene.camera.near, 0.5);
+        assert_eq!(scene.camera.projection, Projection::Orthographic { height: 8.0 });
+    }
+}
@@ -0,0 +1,982 @@
+use nalgebra_glm::Vec3;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::color::Color;
+use crate::nbt::{self, NbtError, Tag};
+use crate::ray_intersect::Material;
+use crate::voxel::{BlockId, VoxelGrid, AIR};
+
+// Límite de bloques de una estructura importada (256³), para no agotar la memoria con un recorte enorme
+const MAX_VOLUME: usize = 256 * 256 * 256;
+
+// Tamaño de los archivos de región: 32x32 chunks de 16x16 columnas
+const REGION_CHUNKS: usize = 32;
+const SECTOR_SIZE: usize = 4096;
+
+// Primer DataVersion (1.16) en que los índices de bloque no cruzan de un long al siguiente
+const NON_SPANNING_DATA_VERSION: i64 = 2566;
+
+// Color sRGB de una cara
+type Rgb = [u8; 3];
+
+// Colores aproximados (arriba, lados, abajo) de los bloques más comunes, para importar
+// estructuras sin resource pack
+const BLOCK_COLORS: &[(&str, Rgb, Rgb, Rgb)] = &[
+    ("stone", [125, 125, 125], [125, 125, 125], [125, 125, 125]),
+    ("cobblestone", [110, 110, 110], [110, 110, 110], [110, 110, 110]),
+    ("deepslate", [80, 80, 82], [80, 80, 82], [80, 80, 82]),
+    ("granite", [150, 105, 85], [150, 105, 85], [150, 105, 85]),
+    ("diorite", [190, 190, 190], [190, 190, 190], [190, 190, 190]),
+    ("andesite", [135, 135, 135], [135, 135, 135], [135, 135, 135]),
+    ("bedrock", [60, 60, 60], [60, 60, 60], [60, 60, 60]),
+    ("dirt", [134, 96, 67], [134, 96, 67], [134, 96, 67]),
+    ("coarse_dirt", [119, 85, 59], [119, 85, 59], [119, 85, 59]),
+    ("grass_block", [95, 160, 60], [120, 110, 65], [134, 96, 67]),
+    ("podzol", [90, 63, 28], [120, 85, 55], [134, 96, 67]),
+    ("sand", [219, 207, 163], [219, 207, 163], [219, 207, 163]),
+    ("red_sand", [190, 102, 33], [190, 102, 33], [190, 102, 33]),
+    ("sandstone", [216, 203, 155], [216, 203, 155], [216, 203, 155]),
+    ("gravel", [131, 127, 126], [131, 127, 126], [131, 127, 126]),
+    ("clay", [160, 166, 179], [160, 166, 179], [160, 166, 179]),
+    ("snow_block", [249, 254, 254], [249, 254, 254], [249, 254, 254]),
+    ("ice", [145, 183, 253], [145, 183, 253], [145, 183, 253]),
+    ("water", [63, 118, 228], [63, 118, 228], [63, 118, 228]),
+    ("lava", [207, 92, 20], [207, 92, 20], [207, 92, 20]),
+    ("oak_log", [151, 122, 73], [109, 85, 50], [151, 122, 73]),
+    ("spruce_log", [108, 80, 46], [58, 37, 16], [108, 80, 46]),
+    ("birch_log", [193, 179, 135], [216, 215, 210], [193, 179, 135]),
+    ("oak_planks", [162, 130, 78], [162, 130, 78], [162, 130, 78]),
+    ("spruce_planks", [114, 84, 48], [114, 84, 48], [114, 84, 48]),
+    ("birch_planks", [192, 175, 121], [192, 175, 121], [192, 175, 121]),
+    ("oak_leaves", [60, 120, 30], [60, 120, 30], [60, 120, 30]),
+    ("spruce_leaves", [50, 90, 50], [50, 90, 50], [50, 90, 50]),
+    ("birch_leaves", [80, 115, 50], [80, 115, 50], [80, 115, 50]),
+    ("bricks", [150, 97, 83], [150, 97, 83], [150, 97, 83]),
+    ("stone_bricks", [122, 121, 122], [122, 121, 122], [122, 121, 122]),
+    ("glass", [200, 220, 230], [200, 220, 230], [200, 220, 230]),
+    ("white_wool", [234, 236, 236], [234, 236, 236], [234, 236, 236]),
+    ("glowstone", [171, 131, 84], [171, 131, 84], [171, 131, 84]),
+    ("coal_ore", [105, 105, 105], [105, 105, 105], [105, 105, 105]),
+    ("iron_ore", [136, 130, 127], [136, 130, 127], [136, 130, 127]),
+    ("obsidian", [15, 11, 25], [15, 11, 25], [15, 11, 25]),
+    ("netherrack", [97, 38, 38], [97, 38, 38], [97, 38, 38]),
+];
+
+// Errores al importar una estructura o región
+#[derive(Debug)]
+pub enum ImportError {
+    Io { path: PathBuf, source: std::io::Error },
+    Nbt { path: PathBuf, source: NbtError },
+    Unsupported { path: PathBuf, reason: String },
+    Invalid { path: PathBuf, reason: String },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io { path, source } => write!(f, "no se pudo leer {}: {}", path.display(), source),
+            ImportError::Nbt { path, source } => write!(f, "NBT inválido en {}: {}", path.display(), source),
+            ImportError::Unsupported { path, reason } => {
+                write!(f, "formato no soportado en {}: {}", path.display(), reason)
+            }
+            ImportError::Invalid { path, reason } => write!(f, "estructura inválida en {}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImportError::Io { source, .. } => Some(source),
+            ImportError::Nbt { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// Estado de bloque, p. ej. "minecraft:oak_log" con "axis=y"
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockState {
+    pub name: String,
+    pub properties: Vec<(String, String)>,
+}
+
+impl BlockState {
+    pub fn air() -> Self {
+        BlockState { name: "minecraft:air".to_string(), properties: Vec::new() }
+    }
+
+    // Formato de texto de los comandos y de las paletas Sponge: "minecraft:oak_log[axis=y]"
+    pub fn parse(state: &str) -> Self {
+        let (name, properties) = match state.split_once('[') {
+            Some((name, rest)) => (name, rest.trim_end_matches(']')),
+            None => (state, ""),
+        };
+        let mut properties: Vec<(String, String)> = properties
+            .split(',')
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        properties.sort();
+        BlockState { name: qualified(name), properties }
+    }
+
+    // Formato NBT de las paletas de estructuras y regiones: { Name, Properties }
+    fn from_nbt(tag: &Tag) -> Option<Self> {
+        let name = tag.get("Name")?.as_str()?;
+        let mut properties: Vec<(String, String)> = tag
+            .get("Properties")
+            .and_then(Tag::as_compound)
+            .map(|props| {
+                props
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        properties.sort();
+        Some(BlockState { name: qualified(name), properties })
+    }
+
+    // Nombre sin el espacio de nombres "minecraft:"
+    pub fn short_name(&self) -> &str {
+        self.name.strip_prefix("minecraft:").unwrap_or(&self.name)
+    }
+
+    pub fn is_air(&self) -> bool {
+        matches!(self.short_name(), "air" | "cave_air" | "void_air" | "structure_void")
+    }
+}
+
+fn qualified(name: &str) -> String {
+    if name.contains(':') {
+        name.to_string()
+    } else {
+        format!("minecraft:{}", name)
+    }
+}
+
+// Bloques importados de un archivo de Minecraft, en una caja densa indexada por (x, y, z)
+pub struct Structure {
+    size: [usize; 3],
+    palette: Vec<BlockState>, // El índice 0 siempre es aire
+    ids: HashMap<BlockState, u32>,
+    blocks: Vec<u32>,
+}
+
+impl Structure {
+    pub fn new(size: [usize; 3]) -> Self {
+        Structure {
+            size,
+            palette: vec![BlockState::air()],
+            ids: HashMap::new(),
+            blocks: vec![0; size[0] * size[1] * size[2]],
+        }
+    }
+
+    pub fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    pub fn palette(&self) -> &[BlockState] {
+        &self.palette
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> &BlockState {
+        &self.palette[self.blocks[self.index(x, y, z)] as usize]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, state: &BlockState) {
+        let id = self.add_state(state);
+        self.set_id(x, y, z, id);
+    }
+
+    // Índice del estado en la paleta, agregándolo si hace falta
+    pub fn add_state(&mut self, state: &BlockState) -> u32 {
+        if state.is_air() {
+            return 0;
+        }
+        if let Some(&id) = self.ids.get(state) {
+            return id;
+        }
+        let id = self.palette.len() as u32;
+        self.palette.push(state.clone());
+        self.ids.insert(state.clone(), id);
+        id
+    }
+
+    // Como `set`, con un índice de `add_state`
+    pub fn set_id(&mut self, x: usize, y: usize, z: usize, id: u32) {
+        let index = self.index(x, y, z);
+        self.blocks[index] = id;
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (y * self.size[2] + z) * self.size[0] + x
+    }
+
+    // Cargar un esquemático Sponge (.schem), Litematica (.litematic) o una estructura de vanilla (.nbt)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        let parser: fn(&Tag) -> Result<Structure, String> = match extension.as_str() {
+            "schem" => parse_sponge,
+            "litematic" => parse_litematic,
+            "nbt" => parse_vanilla,
+            "mca" => {
+                return Err(ImportError::Unsupported {
+                    path: path.to_path_buf(),
+                    reason: "las regiones se cargan con `Structure::load_region` indicando la zona".to_string(),
+                })
+            }
+            _ => {
+                return Err(ImportError::Unsupported {
+                    path: path.to_path_buf(),
+                    reason: "se esperaba .schem, .litematic o .nbt".to_string(),
+                })
+            }
+        };
+
+        let root = read_nbt(path)?;
+        parser(&root).map_err(|reason| ImportError::Invalid { path: path.to_path_buf(), reason })
+    }
+
+    // Recortar de un archivo de región Anvil (.mca, 1.16 o más nuevo) los bloques entre
+    // `from` y `to` (coordenadas del mundo, inclusivas)
+    pub fn load_region<P: AsRef<Path>>(path: P, from: [i32; 3], to: [i32; 3]) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        let invalid = |reason: String| ImportError::Invalid { path: path.to_path_buf(), reason };
+
+        let min = [0, 1, 2].map(|a| from[a].min(to[a]));
+        let max = [0, 1, 2].map(|a| from[a].max(to[a]));
+        let size = checked_size([0, 1, 2].map(|a| max[a] as i64 - min[a] as i64 + 1))
+            .map_err(|reason| invalid(format!("zona {:?} a {:?}: {}", from, to, reason)))?;
+
+        let bytes = fs::read(path).map_err(|source| ImportError::Io { path: path.to_path_buf(), source })?;
+        if bytes.len() < 2 * SECTOR_SIZE {
+            return Err(invalid("falta la cabecera de la región".to_string()));
+        }
+
+        let mut structure = Structure::new(size);
+        for slot in 0..REGION_CHUNKS * REGION_CHUNKS {
+            let entry = &bytes[slot * 4..slot * 4 + 4];
+            let offset = u32::from_be_bytes([0, entry[0], entry[1], entry[2]]) as usize * SECTOR_SIZE;
+            if offset == 0 {
+                continue; // Chunk sin generar
+            }
+            let chunk = read_region_chunk(&bytes, offset).map_err(|reason| invalid(format!("chunk {}: {}", slot, reason)))?;
+            let root = nbt::from_bytes(chunk).map_err(|source| ImportError::Nbt { path: path.to_path_buf(), source })?;
+            copy_chunk(&root, &mut structure, min, max).map_err(|reason| match reason {
+                ChunkError::Unsupported(reason) => ImportError::Unsupported { path: path.to_path_buf(), reason },
+                ChunkError::Invalid(reason) => invalid(format!("chunk {}: {}", slot, reason)),
+            })?;
+        }
+        Ok(structure)
+    }
+
+    // Convertir los bloques en una `VoxelGrid` cuyo bloque (0, 0, 0) tiene centro en `origin`.
+    // `materials` da los materiales de cada estado; los que no conoce usan `placeholder` y
+    // se devuelven sus nombres para reportarlos
+    pub fn to_voxel_grid<F>(&self, origin: Vec3, mut materials: F, placeholder: &Arc<Material>) -> (VoxelGrid, Vec<String>)
+    where
+        F: FnMut(&BlockState) -> Option<[Arc<Material>; 6]>,
+    {
+        let mut grid = VoxelGrid::new(origin - Vec3::new(0.5, 0.5, 0.5), self.size, 1.0);
+        let mut unknown = Vec::new();
+
+        // Un tipo de bloque por nombre: las propiedades (orientación, ...) no cambian el material
+        let mut by_name: HashMap<&str, BlockId> = HashMap::new();
+        let mut placeholder_id = None;
+        let ids: Vec<BlockId> = self
+            .palette
+            .iter()
+            .map(|state| {
+                if state.is_air() {
+                    return AIR;
+                }
+                if let Some(&id) = by_name.get(state.name.as_str()) {
+                    return id;
+                }
+                let id = match materials(state) {
+                    Some(faces) if by_name.len() < BlockId::MAX as usize - 1 => grid.add_block_type(faces),
+                    _ => {
+                        unknown.push(state.name.clone());
+                        *placeholder_id.get_or_insert_with(|| {
+                            grid.add_block_type(std::array::from_fn(|_| Arc::clone(placeholder)))
+                        })
+                    }
+                };
+                by_name.insert(&state.name, id);
+                id
+            })
+            .collect();
+
+        for y in 0..self.size[1] {
+            for z in 0..self.size[2] {
+                for x in 0..self.size[0] {
+                    let id = ids[self.blocks[self.index(x, y, z)] as usize];
+                    if id != AIR {
+                        grid.set(x, y, z, id);
+                    }
+                }
+            }
+        }
+        (grid, unknown)
+    }
+}
+
+// Bloques que emiten luz: color de la luz y nivel de luz de Minecraft (0 a 15)
+const BLOCK_LIGHT: &[(&str, Rgb, u8)] = &[
+    ("glowstone", [255, 214, 140], 15),
+    ("lava", [255, 120, 30], 15),
+    ("sea_lantern", [200, 235, 255], 15),
+    ("shroomlight", [255, 170, 90], 15),
+    ("jack_o_lantern", [255, 190, 90], 15),
+    ("lantern", [255, 200, 120], 15),
+    ("campfire", [255, 160, 70], 15),
+    ("fire", [255, 150, 50], 15),
+    ("beacon", [210, 240, 255], 15),
+    ("ochre_froglight", [255, 230, 150], 15),
+    ("verdant_froglight", [210, 255, 170], 15),
+    ("pearlescent_froglight", [255, 210, 240], 15),
+    ("torch", [255, 190, 100], 14),
+    ("wall_torch", [255, 190, 100], 14),
+    ("end_rod", [240, 235, 255], 14),
+    ("soul_torch", [120, 220, 255], 10),
+    ("soul_wall_torch", [120, 220, 255], 10),
+    ("soul_lantern", [120, 220, 255], 10),
+    ("magma_block", [255, 100, 30], 3),
+];
+
+// Color y fuerza (nivel de luz / 15) de la luz que emite un bloque, o `None` si no emite
+pub fn block_emission(name: &str) -> Option<(Color, f32)> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    let &(_, [r, g, b], level) = BLOCK_LIGHT.iter().find(|(block, ..)| *block == name)?;
+    Some((Color::new(r, g, b), level as f32 / 15.0))
+}
+
+// Las caras de un bloque que emite luz, con la emisión de la tabla si sus materiales no
+// traen una propia
+pub fn with_block_emission(name: &str, faces: [Arc<Material>; 6]) -> [Arc<Material>; 6] {
+    match block_emission(name) {
+        Some((emission, strength)) if !faces.iter().any(|face| face.is_emissive()) => {
+            faces.map(|face| Arc::new((*face).clone().with_emission(emission, strength)))
+        }
+        _ => faces,
+    }
+}
+
+// Material magenta para los bloques que no sabemos dibujar, bien visible a propósito
+pub fn placeholder_material() -> Material {
+    Material::new(Color::new(255, 0, 255), 1.0, [1.0, 0.0, 0.0, 0.0], 1.0, false, None)
+}
+
+// Materiales de color liso para los bloques comunes, o `None` si no está en la tabla
+pub fn builtin_block_materials(name: &str) -> Option<[Arc<Material>; 6]> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    let &(_, top, side, bottom) = BLOCK_COLORS.iter().find(|(block, ..)| *block == name)?;
+    let material = |[r, g, b]: Rgb| {
+        Arc::new(Material::new(Color::new(r, g, b), 32.0, [1.0, 0.1, 0.0, 0.0], 1.0, false, None))
+    };
+    let (top, side, bottom) = (material(top), material(side), material(bottom));
+    Some([side.clone(), side.clone(), bottom, top, side.clone(), side])
+}
+
+fn read_nbt(path: &Path) -> Result<Tag, ImportError> {
+    let bytes = fs::read(path).map_err(|source| ImportError::Io { path: path.to_path_buf(), source })?;
+    nbt::from_bytes(&bytes).map_err(|source| ImportError::Nbt { path: path.to_path_buf(), source })
+}
+
+fn field<'a>(tag: &'a Tag, key: &str) -> Result<&'a Tag, String> {
+    tag.get(key).ok_or_else(|| format!("falta el campo \"{}\"", key))
+}
+
+fn int(tag: &Tag, key: &str) -> Result<i64, String> {
+    field(tag, key)?.as_i64().ok_or_else(|| format!("\"{}\" no es un entero", key))
+}
+
+fn checked_size(size: [i64; 3]) -> Result<[usize; 3], String> {
+    if size.iter().any(|&s| s <= 0) {
+        return Err(format!("tamaño inválido {:?}", size));
+    }
+    let size = size.map(|s| s as usize);
+    if size.iter().try_fold(1usize, |acc, &s| acc.checked_mul(s)).is_none_or(|v| v > MAX_VOLUME) {
+        return Err(format!("la estructura {:?} es demasiado grande (máximo {} bloques)", size, MAX_VOLUME));
+    }
+    Ok(size)
+}
+
+// Sponge Schematic v2 (raíz con Palette/BlockData) y v3 (todo dentro de "Schematic" y "Blocks")
+fn parse_sponge(root: &Tag) -> Result<Structure, String> {
+    let schematic = root.get("Schematic").unwrap_or(root);
+    // Las dimensiones son shorts sin signo
+    let dimension = |key| int(schematic, key).map(|v| v & 0xffff);
+    let size = checked_size([dimension("Width")?, dimension("Height")?, dimension("Length")?])?;
+
+    let (palette, data) = match schematic.get("Blocks") {
+        Some(blocks) => (field(blocks, "Palette")?, field(blocks, "Data")?),
+        None => (field(schematic, "Palette")?, field(schematic, "BlockData")?),
+    };
+    let palette = palette.as_compound().ok_or("\"Palette\" no es un compound")?;
+    let mut structure = Structure::new(size);
+    let mut ids = vec![0; palette.len()];
+    for (state, index) in palette {
+        let index = index.as_i64().filter(|&i| i >= 0 && (i as usize) < ids.len());
+        let index = index.ok_or_else(|| format!("índice de paleta inválido para {}", state))?;
+        ids[index as usize] = structure.add_state(&BlockState::parse(state));
+    }
+
+    // Índices de paleta codificados como varints, en orden (y, z, x)
+    let data = data.as_byte_array().ok_or("los datos de bloques no son un byte array")?;
+    let mut bytes = data.iter().map(|&b| b as u8);
+    for y in 0..size[1] {
+        for z in 0..size[2] {
+            for x in 0..size[0] {
+                let index = read_varint(&mut bytes).ok_or("faltan datos de bloques")?;
+                let id = *ids.get(index).ok_or_else(|| format!("índice de paleta {} fuera de rango", index))?;
+                structure.set_id(x, y, z, id);
+            }
+        }
+    }
+    Ok(structure)
+}
+
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<usize> {
+    let mut value = 0usize;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+// Litematica: una o más regiones con su posición, tamaño (puede ser negativo) y estados empaquetados
+fn parse_litematic(root: &Tag) -> Result<Structure, String> {
+    let regions = field(root, "Regions")?.as_compound().ok_or("\"Regions\" no es un compound")?;
+
+    // Caja de cada región en coordenadas del esquemático
+    let mut boxes = Vec::new();
+    for (name, region) in regions {
+        let position = field(region, "Position")?;
+        let size = field(region, "Size")?;
+        let mut min = [0i64; 3];
+        let mut extent = [0i64; 3];
+        for (axis, key) in ["x", "y", "z"].iter().enumerate() {
+            let p = int(position, key)?;
+            let s = int(size, key)?;
+            min[axis] = if s < 0 { p + s + 1 } else { p };
+            extent[axis] = s.abs();
+        }
+        let extent = checked_size(extent).map_err(|e| format!("región {}: {}", name, e))?;
+        boxes.push((name, region, min, extent));
+    }
+    if boxes.is_empty() {
+        return Err("el esquemático no tiene regiones".to_string());
+    }
+
+    let lo = [0, 1, 2].map(|a| boxes.iter().map(|b| b.2[a]).min().unwrap_or(0));
+    let hi = [0, 1, 2].map(|a| boxes.iter().map(|b| b.2[a] + b.3[a] as i64).max().unwrap_or(0));
+    let mut structure = Structure::new(checked_size([0, 1, 2].map(|a| hi[a] - lo[a]))?);
+
+    for (name, region, min, extent) in boxes {
+        let palette: Vec<u32> = field(region, "BlockStatePalette")?
+            .as_list()
+            .ok_or("la paleta no es una lista")?
+            .iter()
+            .map(|tag| {
+                let state = BlockState::from_nbt(tag).ok_or_else(|| format!("región {}: estado de bloque inválido", name))?;
+                Ok(structure.add_state(&state))
+            })
+            .collect::<Result<_, String>>()?;
+        let states = field(region, "BlockStates")?.as_long_array().ok_or("BlockStates no es un long array")?;
+        let bits = bits_for(palette.len(), 2);
+
+        let mut i = 0;
+        for y in 0..extent[1] {
+            for z in 0..extent[2] {
+                for x in 0..extent[0] {
+                    let index = packed_spanning(states, bits, i).ok_or_else(|| format!("región {}: faltan estados", name))?;
+                    let id = *palette.get(index).ok_or_else(|| format!("región {}: índice {} fuera de rango", name, index))?;
+                    let at = [x, y, z].map(|c| c as i64);
+                    let [sx, sy, sz] = [0, 1, 2].map(|a| (min[a] + at[a] - lo[a]) as usize);
+                    structure.set_id(sx, sy, sz, id);
+                    i += 1;
+                }
+            }
+        }
+    }
+    Ok(structure)
+}
+
+// Estructura de vanilla (bloques de estructura): lista dispersa de bloques con posición y estado
+fn parse_vanilla(root: &Tag) -> Result<Structure, String> {
+    let size = field(root, "size")?.as_int_array().filter(|s| s.len() == 3).ok_or("\"size\" debe tener 3 enteros")?;
+    let size = checked_size([size[0] as i64, size[1] as i64, size[2] as i64])?;
+
+    // Las estructuras con variantes traen varias paletas; usamos la primera
+    let palette = match root.get("palette") {
+        Some(palette) => palette,
+        None => field(root, "palettes")?.as_list().and_then(|p| p.first()).ok_or("\"palettes\" está vacío")?,
+    };
+    let mut structure = Structure::new(size);
+    let palette: Vec<u32> = palette
+        .as_list()
+        .ok_or("la paleta no es una lista")?
+        .iter()
+        .map(|tag| BlockState::from_nbt(tag).map(|state| structure.add_state(&state)).ok_or("estado de bloque inválido"))
+        .collect::<Result<_, _>>()?;
+
+    for block in field(root, "blocks")?.as_list().ok_or("\"blocks\" no es una lista")? {
+        let pos = field(block, "pos")?.as_int_array().filter(|p| p.len() == 3).ok_or("\"pos\" debe tener 3 enteros")?;
+        let state = int(block, "state")?;
+        let id = *palette.get(state as usize).ok_or_else(|| format!("estado {} fuera de la paleta", state))?;
+        if pos.iter().zip(&size).any(|(&p, &s)| p < 0 || p as usize >= s) {
+            return Err(format!("bloque {:?} fuera de la estructura", pos));
+        }
+        structure.set_id(pos[0] as usize, pos[1] as usize, pos[2] as usize, id);
+    }
+    Ok(structure)
+}
+
+// Bits por índice de una paleta de `len` estados
+fn bits_for(len: usize, min_bits: u32) -> u32 {
+    let needed = usize::BITS - len.saturating_sub(1).leading_zeros();
+    needed.max(min_bits)
+}
+
+// Índice `i` de un arreglo de longs donde los valores pueden cruzar de un long al siguiente (Litematica)
+fn packed_spanning(longs: &[i64], bits: u32, i: usize) -> Option<usize> {
+    let bit = i * bits as usize;
+    let (word, offset) = (bit / 64, (bit % 64) as u32);
+    let mask = (1u64 << bits) - 1;
+    let mut value = (*longs.get(word)? as u64) >> offset;
+    if offset + bits > 64 {
+        value |= (*longs.get(word + 1)? as u64) << (64 - offset);
+    }
+    Some((value & mask) as usize)
+}
+
+// Índice `i` de un arreglo de longs donde cada long guarda un número entero de valores (Anvil 1.16+)
+fn packed_aligned(longs: &[i64], bits: u32, i: usize) -> Option<usize> {
+    let per_long = (64 / bits) as usize;
+    let word = *longs.get(i / per_long)? as u64;
+    let offset = (i % per_long) as u32 * bits;
+    Some(((word >> offset) & ((1u64 << bits) - 1)) as usize)
+}
+
+// Datos comprimidos de un chunk dentro del archivo de región
+fn read_region_chunk(bytes: &[u8], offset: usize) -> Result<&[u8], String> {
+    let header = bytes.get(offset..offset + 5).ok_or("el chunk apunta fuera del archivo")?;
+    let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    if header[4] & 0x80 != 0 {
+        return Err("el chunk está guardado en un archivo .mcc aparte".to_string());
+    }
+    if !matches!(header[4], 1..=3) {
+        return Err(format!("compresión {} no soportada", header[4]));
+    }
+    // `length` incluye el byte de compresión; `nbt::from_bytes` detecta gzip o zlib
+    bytes.get(offset + 5..offset + 4 + length).ok_or_else(|| "el chunk está truncado".to_string())
+}
+
+enum ChunkError {
+    Unsupported(String),
+    Invalid(String),
+}
+
+// Copiar los bloques de un chunk que caen dentro de [min, max]
+fn copy_chunk(root: &Tag, structure: &mut Structure, min: [i32; 3], max: [i32; 3]) -> Result<(), ChunkError> {
+    let invalid = |reason: &str| ChunkError::Invalid(reason.to_string());
+
+    // 1.18+ guarda todo en la raíz; antes, dentro de "Level"
+    let level = root.get("Level").unwrap_or(root);
+    let data_version = root.get("DataVersion").and_then(Tag::as_i64).unwrap_or(0);
+    if data_version < NON_SPANNING_DATA_VERSION {
+        return Err(ChunkError::Unsupported(format!(
+            "chunk de una versión anterior a 1.16 (DataVersion {})",
+            data_version
+        )));
+    }
+
+    let (min, max) = (min.map(i64::from), max.map(i64::from));
+    let base_x = block_coordinate(level.get("xPos"), "xPos")?;
+    let base_z = block_coordinate(level.get("zPos"), "zPos")?;
+    if base_x > max[0] || base_x + 15 < min[0] || base_z > max[2] || base_z + 15 < min[2] {
+        return Ok(());
+    }
+
+    let sections = level
+        .get("sections")
+        .or_else(|| level.get("Sections"))
+        .and_then(Tag::as_list)
+        .unwrap_or(&[]);
+    for section in sections {
+        let base_y = block_coordinate(section.get("Y"), "Y")?;
+        if base_y > max[1] || base_y + 15 < min[1] {
+            continue;
+        }
+
+        let (palette, data) = match section.get("block_states") {
+            Some(states) => (states.get("palette"), states.get("data")),
+            None => (section.get("Palette"), section.get("BlockStates")),
+        };
+        let Some(palette) = palette.and_then(Tag::as_list) else {
+            continue; // Sección vacía (solo luz)
+        };
+        let palette: Vec<u32> = palette
+            .iter()
+            .map(|tag| {
+                let state = BlockState::from_nbt(tag).ok_or_else(|| invalid("estado de bloque inválido"))?;
+                Ok(structure.add_state(&state))
+            })
+            .collect::<Result<_, ChunkError>>()?;
+        let data = data.and_then(Tag::as_long_array).unwrap_or(&[]);
+        let bits = bits_for(palette.len(), 4);
+
+        for y in 0..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    let world = [base_x + x, base_y + y, base_z + z];
+                    if (0..3).any(|a| world[a] < min[a] || world[a] > max[a]) {
+                        continue;
+                    }
+                    // Con un solo estado en la paleta no hay datos
+                    let index = if palette.len() == 1 {
+                        0
+                    } else {
+                        let i = ((y * 16 + z) * 16 + x) as usize;
+                        packed_aligned(data, bits, i).ok_or_else(|| invalid("faltan datos de bloques"))?
+                    };
+                    let id = *palette.get(index).ok_or_else(|| invalid("índice de paleta fuera de rango"))?;
+                    let [sx, sy, sz] = [0, 1, 2].map(|a| (world[a] - min[a]) as usize);
+                    structure.set_id(sx, sy, sz, id);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Primer bloque de un chunk o sección a partir de su coordenada en chunks; los datos corruptos
+// pueden traer cualquier entero, así que se valida antes de multiplicar
+fn block_coordinate(tag: Option<&Tag>, key: &str) -> Result<i64, ChunkError> {
+    let value = tag.and_then(Tag::as_i64).ok_or_else(|| ChunkError::Invalid(format!("falta {}", key)))?;
+    i32::try_from(value)
+        .ok()
+        .and_then(|chunk| chunk.checked_mul(16))
+        .map(i64::from)
+        .ok_or_else(|| ChunkError::Invalid(format!("{} fuera de rango: {}", key, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn compound(fields: Vec<(&str, Tag)>) -> Tag {
+        Tag::Compound(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    fn string(s: &str) -> Tag {
+        Tag::String(s.to_string())
+    }
+
+    fn state(name: &str) -> Tag {
+        compound(vec![("Name", string(name))])
+    }
+
+    fn id(tag: &Tag) -> u8 {
+        match tag {
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    // Codifica un payload NBT, para armar los archivos de prueba
+    fn write_payload(tag: &Tag, out: &mut Vec<u8>) {
+        let write_str = |s: &str, out: &mut Vec<u8>| {
+            out.extend((s.len() as u16).to_be_bytes());
+            out.extend(s.as_bytes());
+        };
+        match tag {
+            Tag::Byte(v) => out.push(*v as u8),
+            Tag::Short(v) => out.extend(v.to_be_bytes()),
+            Tag::Int(v) => out.extend(v.to_be_bytes()),
+            Tag::Long(v) => out.extend(v.to_be_bytes()),
+            Tag::Float(v) => out.extend(v.to_be_bytes()),
+            Tag::Double(v) => out.extend(v.to_be_bytes()),
+            Tag::ByteArray(values) => {
+                out.extend((values.len() as i32).to_be_bytes());
+                out.extend(values.iter().map(|&b| b as u8));
+            }
+            Tag::String(s) => write_str(s, out),
+            Tag::List(items) => {
+                out.push(items.first().map_or(0, id));
+                out.extend((items.len() as i32).to_be_bytes());
+                for item in items {
+                    write_payload(item, out);
+                }
+            }
+            Tag::Compound(fields) => {
+                for (name, value) in fields {
+                    out.push(id(value));
+                    write_str(name, out);
+                    write_payload(value, out);
+                }
+                out.push(0);
+            }
+            Tag::IntArray(values) => {
+                out.extend((values.len() as i32).to_be_bytes());
+                values.iter().for_each(|v| out.extend(v.to_be_bytes()));
+            }
+            Tag::LongArray(values) => {
+                out.extend((values.len() as i32).to_be_bytes());
+                values.iter().for_each(|v| out.extend(v.to_be_bytes()));
+            }
+        }
+    }
+
+    // Archivo NBT con el compound raíz sin nombre
+    fn nbt_file(root: &Tag) -> Vec<u8> {
+        let mut bytes = vec![10, 0, 0];
+        write_payload(root, &mut bytes);
+        bytes
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    // Archivo temporal con un nombre único por prueba
+    fn fixture(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("diorama-structure-{}-{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn names(structure: &Structure, blocks: &[[usize; 3]]) -> Vec<String> {
+        blocks.iter().map(|&[x, y, z]| structure.get(x, y, z).short_name().to_string()).collect()
+    }
+
+    fn sponge_v2(data: Vec<i8>) -> Tag {
+        compound(vec![
+            ("Width", Tag::Short(2)),
+            ("Height", Tag::Short(1)),
+            ("Length", Tag::Short(2)),
+            ("Palette", compound(vec![("minecraft:air", Tag::Int(0)), ("minecraft:oak_log[axis=y]", Tag::Int(1))])),
+            ("BlockData", Tag::ByteArray(data)),
+        ])
+    }
+
+    #[test]
+    fn sponge_v2_and_v3_are_read_in_yzx_order() {
+        let v2 = sponge_v2(vec![1, 0, 0, 1]);
+        let structure = parse_sponge(&v2).unwrap();
+        assert_eq!(structure.size(), [2, 1, 2]);
+        assert_eq!(names(&structure, &[[0, 0, 0], [1, 0, 0], [0, 0, 1], [1, 0, 1]]), ["oak_log", "air", "air", "oak_log"]);
+        assert_eq!(structure.get(0, 0, 0).properties, vec![("axis".to_string(), "y".to_string())]);
+
+        let v3 = compound(vec![(
+            "Schematic",
+            compound(vec![
+                ("Width", Tag::Short(1)),
+                ("Height", Tag::Short(2)),
+                ("Length", Tag::Short(1)),
+                (
+                    "Blocks",
+                    compound(vec![
+                        ("Palette", compound(vec![("minecraft:stone", Tag::Int(0)), ("minecraft:air", Tag::Int(1))])),
+                        ("Data", Tag::ByteArray(vec![0, 1])),
+                    ]),
+                ),
+            ]),
+        )]);
+        let path = fixture("v3.schem", &gzip(&nbt_file(&v3)));
+        let structure = Structure::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(names(&structure, &[[0, 0, 0], [0, 1, 0]]), ["stone", "air"]);
+    }
+
+    #[test]
+    fn sponge_with_missing_or_invalid_data_is_rejected() {
+        assert!(parse_sponge(&sponge_v2(vec![1, 0, 0])).err().unwrap().contains("faltan datos"));
+        assert!(parse_sponge(&sponge_v2(vec![1, 0, 0, 5])).err().unwrap().contains("fuera de rango"));
+        // Un varint que no termina
+        assert!(parse_sponge(&sponge_v2(vec![-1, -1, -1, -1, -1, -1])).is_err());
+        let mut huge = sponge_v2(vec![]);
+        if let Tag::Compound(fields) = &mut huge {
+            fields.insert("Width".to_string(), Tag::Short(-1));
+            fields.insert("Height".to_string(), Tag::Short(-1));
+        }
+        assert!(parse_sponge(&huge).err().unwrap().contains("demasiado grande"));
+    }
+
+    fn litematic(size_x: i32, states: Vec<i64>) -> Tag {
+        let xyz = |x, y, z| compound(vec![("x", Tag::Int(x)), ("y", Tag::Int(y)), ("z", Tag::Int(z))]);
+        compound(vec![(
+            "Regions",
+            compound(vec![(
+                "casa",
+                compound(vec![
+                    ("Position", xyz(0, 0, 0)),
+                    ("Size", xyz(size_x, 1, 1)),
+                    ("BlockStatePalette", Tag::List(vec![state("air"), state("stone"), state("glass")])),
+                    ("BlockStates", Tag::LongArray(states)),
+                ]),
+            )]),
+        )])
+    }
+
+    #[test]
+    fn litematic_regions_with_negative_size_are_read() {
+        // Índices de 2 bits: stone, glass, air
+        let structure = parse_litematic(&litematic(-3, vec![0b00_10_01])).unwrap();
+        assert_eq!(structure.size(), [3, 1, 1]);
+        assert_eq!(names(&structure, &[[0, 0, 0], [1, 0, 0], [2, 0, 0]]), ["stone", "glass", "air"]);
+    }
+
+    #[test]
+    fn litematic_values_can_span_two_longs() {
+        // 33 valores de 2 bits: el último cae en el segundo long
+        let structure = parse_litematic(&litematic(33, vec![0, 0b10])).unwrap();
+        assert_eq!(names(&structure, &[[0, 0, 0], [32, 0, 0]]), ["air", "glass"]);
+    }
+
+    #[test]
+    fn litematic_with_missing_states_is_rejected() {
+        assert!(parse_litematic(&litematic(33, vec![0])).err().unwrap().contains("faltan estados"));
+        assert!(parse_litematic(&litematic(1, vec![0b11])).err().unwrap().contains("fuera de rango"));
+        assert!(parse_litematic(&compound(vec![("Regions", compound(vec![]))])).is_err());
+    }
+
+    fn vanilla(pos: [i32; 3], state_index: i32) -> Tag {
+        compound(vec![
+            ("size", Tag::List(vec![Tag::Int(1), Tag::Int(2), Tag::Int(1)])),
+            ("palette", Tag::List(vec![state("stone"), state("air")])),
+            (
+                "blocks",
+                Tag::List(vec![compound(vec![
+                    ("pos", Tag::List(pos.iter().map(|&p| Tag::Int(p)).collect())),
+                    ("state", Tag::Int(state_index)),
+                ])]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn vanilla_structures_are_read() {
+        let path = fixture("casa.nbt", &gzip(&nbt_file(&vanilla([0, 1, 0], 0))));
+        let structure = Structure::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(names(&structure, &[[0, 0, 0], [0, 1, 0]]), ["air", "stone"]);
+    }
+
+    #[test]
+    fn vanilla_blocks_outside_the_structure_are_rejected() {
+        assert!(parse_vanilla(&vanilla([0, 2, 0], 0)).err().unwrap().contains("fuera de la estructura"));
+        assert!(parse_vanilla(&vanilla([0, -1, 0], 0)).is_err());
+        assert!(parse_vanilla(&vanilla([0, 0, 0], 7)).err().unwrap().contains("fuera de la paleta"));
+    }
+
+    #[test]
+    fn truncated_and_unknown_files_are_reported() {
+        let bytes = gzip(&nbt_file(&vanilla([0, 0, 0], 0)));
+        let path = fixture("cortado.nbt", &bytes[..bytes.len() / 2]);
+        let result = Structure::load(&path);
+        fs::remove_file(path).unwrap();
+        assert!(matches!(result, Err(ImportError::Nbt { .. })));
+
+        let path = fixture("campos.nbt", &nbt_file(&compound(vec![("size", Tag::Int(3))])));
+        let result = Structure::load(&path);
+        fs::remove_file(path).unwrap();
+        assert!(matches!(result, Err(ImportError::Invalid { .. })));
+
+        assert!(matches!(Structure::load("casa.obj"), Err(ImportError::Unsupported { .. })));
+        assert!(matches!(Structure::load("no-existe.schem"), Err(ImportError::Io { .. })));
+    }
+
+    fn chunk(x_pos: Tag, section_y: Tag) -> Tag {
+        let states = compound(vec![
+            ("palette", Tag::List(vec![state("air"), state("dirt")])),
+            // 4 bits por índice: en cada long, el bloque x = 0 es tierra y el resto aire
+            ("data", Tag::LongArray(vec![1; 256])),
+        ]);
+        compound(vec![
+            ("DataVersion", Tag::Int(3465)),
+            ("xPos", x_pos),
+            ("zPos", Tag::Int(0)),
+            ("sections", Tag::List(vec![compound(vec![("Y", section_y), ("block_states", states)])])),
+        ])
+    }
+
+    // Archivo de región con un solo chunk (comprimido con zlib) en la primera entrada
+    fn region(chunk: &Tag) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&nbt_file(chunk)).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let mut bytes = vec![0; 2 * SECTOR_SIZE];
+        bytes[..4].copy_from_slice(&[0, 0, 2, 1]);
+        bytes.extend((data.len() as u32 + 1).to_be_bytes());
+        bytes.push(2);
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn region_chunks_are_cropped() {
+        let path = fixture("r.0.0.mca", &region(&chunk(Tag::Int(0), Tag::Byte(0))));
+        let structure = Structure::load_region(&path, [0, 0, 0], [16, 1, 0]);
+        fs::remove_file(path).unwrap();
+        let structure = structure.unwrap();
+        assert_eq!(structure.size(), [17, 2, 1]);
+        assert_eq!(names(&structure, &[[0, 0, 0], [1, 0, 0], [0, 1, 0], [16, 0, 0]]), ["dirt", "air", "dirt", "air"]);
+    }
+
+    #[test]
+    fn corrupt_chunk_coordinates_are_rejected() {
+        for chunk in [chunk(Tag::Long(i64::MAX), Tag::Byte(0)), chunk(Tag::Int(i32::MAX), Tag::Byte(0)), chunk(Tag::Int(0), Tag::Long(1 << 40))] {
+            let mut structure = Structure::new([1, 1, 1]);
+            assert!(matches!(copy_chunk(&chunk, &mut structure, [0, 0, 0], [0, 0, 0]), Err(ChunkError::Invalid(_))));
+        }
+    }
+
+    #[test]
+    fn huge_regions_are_rejected_before_reading_the_file() {
+        // El tamaño se comprueba antes de abrir el archivo, por eso no hace falta que exista
+        for (from, to) in [([0, 0, 0], [4096, 4096, 4096]), ([i32::MIN; 3], [i32::MAX; 3])] {
+            let result = Structure::load_region("no-existe.mca", from, to);
+            assert!(matches!(result, Err(ImportError::Invalid { .. })), "{:?} {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn truncated_regions_are_rejected() {
+        let bytes = region(&chunk(Tag::Int(0), Tag::Byte(0)));
+        for (name, len) in [("cabecera.mca", SECTOR_SIZE), ("chunk.mca", bytes.len() - 10)] {
+            let path = fixture(name, &bytes[..len]);
+            let result = Structure::load_region(&path, [0, 0, 0], [1, 1, 1]);
+            fs::remove_file(path).unwrap();
+            assert!(matches!(result, Err(ImportError::Invalid { .. })), "{}", name);
+        }
+    }
+}
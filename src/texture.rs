@@ -1,62 +1,746 @@
-use image::{GenericImageView, DynamicImage};
+use image::error::{ImageError, LimitError, LimitErrorKind};
+use image::{DynamicImage, GenericImageView, ImageResult, RgbImage, RgbaImage};
+use std::fmt;
+use std::io;
+use std::ops::{Add, Mul};
+use std::path::{Path, PathBuf};
 use crate::color::Color;
+use crate::radiance::Radiance;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Texture {
-    pub data: Vec<Color>,
+// Espacio de color de los píxeles guardados en el archivo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb,   // Colores normales (albedo): se decodifican a lineal al cargar
+    Linear, // Datos (mapas de normales, rugosidad, ...): se usan tal cual
+}
+
+// Cómo se leen los texels de una textura
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureFilter {
+    #[default]
+    Nearest,   // Texel más cercano: el aspecto pixelado de Minecraft
+    Bilinear,  // Mezcla de los 4 texels vecinos del nivel base
+    Trilinear, // Bilineal entre los dos niveles de mipmap más cercanos al tamaño del píxel
+}
+
+// Rectángulo de texels del nivel base que ocupa una imagen dentro de una textura más grande
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: usize,
+    pub y: usize,
     pub width: usize,
     pub height: usize,
 }
 
-impl Color {
-    pub fn r(&self) -> u8 {
-        self.r
+impl TextureRegion {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        TextureRegion { x, y, width, height }
+    }
+
+    // Nivel de detalle para un píxel que cubre `footprint` unidades UV de la región
+    pub fn lod(&self, footprint: f32) -> f32 {
+        let texels = footprint * self.width.max(self.height) as f32;
+        if texels > 1.0 { texels.log2() } else { 0.0 }
+    }
+}
+
+// Errores al cargar una textura desde disco
+#[derive(Debug)]
+pub enum TextureError {
+    NotFound { path: PathBuf },
+    Io { path: PathBuf, source: io::Error },
+    UnsupportedFormat { path: PathBuf, source: ImageError },
+    Decode { path: PathBuf, source: ImageError },
+}
+
+impl TextureError {
+    fn from_image_error(path: &Path, err: ImageError) -> Self {
+        let path = path.to_path_buf();
+        match err {
+            ImageError::IoError(source) if source.kind() == io::ErrorKind::NotFound => TextureError::NotFound { path },
+            // Un archivo truncado es una imagen corrupta, no un problema del disco
+            ImageError::IoError(source) if source.kind() == io::ErrorKind::UnexpectedEof => {
+                TextureError::Decode { path, source: ImageError::IoError(source) }
+            }
+            ImageError::IoError(source) => TextureError::Io { path, source },
+            ImageError::Unsupported(_) => TextureError::UnsupportedFormat { path, source: err },
+            _ => TextureError::Decode { path, source: err },
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            TextureError::NotFound { path }
+            | TextureError::Io { path, .. }
+            | TextureError::UnsupportedFormat { path, .. }
+            | TextureError::Decode { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::NotFound { path } => write!(f, "no existe la textura {}", path.display()),
+            TextureError::Io { path, source } => {
+                write!(f, "no se pudo leer {}: {}", path.display(), source)
+            }
+            TextureError::UnsupportedFormat { path, source } => {
+                write!(f, "formato de imagen no soportado en {}: {}", path.display(), source)
+            }
+            TextureError::Decode { path, source } => {
+                write!(f, "imagen corrupta en {}: {}", path.display(), source)
+            }
+        }
     }
-    pub fn g(&self) -> u8 {
-        self.g
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::NotFound { .. } => None,
+            TextureError::Io { source, .. } => Some(source),
+            TextureError::UnsupportedFormat { source, .. } => Some(source),
+            TextureError::Decode { source, .. } => Some(source),
+        }
     }
-    pub fn b(&self) -> u8 {
-        self.b
+}
+
+// Abrir y decodificar una imagen, clasificando el error
+pub fn open_image<P: AsRef<Path>>(path: P) -> Result<DynamicImage, TextureError> {
+    let path = path.as_ref();
+    let img = image::open(path).map_err(|err| TextureError::from_image_error(path, err))?;
+    // Una imagen sin píxeles no tiene nada que muestrear
+    if img.width() == 0 || img.height() == 0 {
+        let source = ImageError::Limits(LimitError::from_kind(LimitErrorKind::DimensionError));
+        return Err(TextureError::Decode { path: path.to_path_buf(), source });
     }
+    Ok(img)
+}
+
+// Un nivel reducido de la cadena de mipmaps
+#[derive(Debug, Clone, PartialEq)]
+struct MipLevel {
+    width: usize,
+    height: usize,
+    data: Vec<Radiance>,
+    alpha: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    pub data: Vec<Radiance>,        // Siempre en espacio lineal
+    pub alpha: Option<Vec<f32>>,    // Opacidad por texel en [0, 1]; `None` si la imagen es opaca
+    pub width: usize,
+    pub height: usize,
+    pub color_space: ColorSpace,
+    mips: Vec<MipLevel>,            // Niveles 1.. (la mitad de tamaño cada uno); el nivel 0 es `data`
 }
 
 impl Texture {
-    pub fn load_from_file(path: &str) -> Self {
-        let img = image::open(path).expect("Failed to load texture");
+    // Cargar una textura de color (sRGB)
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, TextureError> {
+        Self::load_with_color_space(path, ColorSpace::Srgb)
+    }
+
+    // Cargar una textura de datos que ya está en espacio lineal
+    pub fn load_linear<P: AsRef<Path>>(path: P) -> Result<Self, TextureError> {
+        Self::load_with_color_space(path, ColorSpace::Linear)
+    }
+
+    pub fn load_with_color_space<P: AsRef<Path>>(path: P, color_space: ColorSpace) -> Result<Self, TextureError> {
+        let img = open_image(path)?;
+        Ok(Self::from_image(&img, color_space))
+    }
+
+    // Tablero magenta y negro de 16x16 (como la textura faltante de Minecraft),
+    // para reemplazar texturas que no se pudieron cargar sin detener el programa
+    pub fn placeholder() -> Self {
+        let magenta = Radiance::from_srgb(Color::new(255, 0, 255));
+        let black = Radiance::new(0.0, 0.0, 0.0);
+        let data = (0..16 * 16)
+            .map(|i| if ((i % 16) / 8 + (i / 16) / 8) % 2 == 0 { magenta } else { black })
+            .collect();
+        Self::from_pixels(16, 16, data, None, ColorSpace::Srgb)
+    }
+
+    // Decodificar una imagen ya cargada
+    pub fn from_image(img: &DynamicImage, color_space: ColorSpace) -> Self {
+        if matches!(img, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)) {
+            return Self::from_hdr_image(img);
+        }
         let (width, height) = img.dimensions();
-        let mut data = Vec::new();
+        let mut data = Vec::with_capacity((width * height) as usize);
+        let mut alpha = Vec::with_capacity((width * height) as usize);
 
         for (_, _, pixel) in img.pixels() {
             let rgba = pixel.0;
+            // El alfa es cobertura, no color: siempre lineal
+            alpha.push(rgba[3] as f32 / 255.0);
             let color = Color::new(rgba[0], rgba[1], rgba[2]);
-            data.push(color);
+            data.push(match color_space {
+                ColorSpace::Srgb => Radiance::from_srgb(color),
+                ColorSpace::Linear => Radiance::new(
+                    rgba[0] as f32 / 255.0,
+                    rgba[1] as f32 / 255.0,
+                    rgba[2] as f32 / 255.0,
+                ),
+            });
         }
 
-        Texture {
-            width: width as usize,
-            height: height as usize,
+        // Las imágenes sin transparencia (JPG, PNG opacos) no guardan el canal
+        let alpha = if alpha.iter().any(|&a| a < 1.0) { Some(alpha) } else { None };
+
+        Self::from_pixels(width as usize, height as usize, data, alpha, color_space)
+    }
+
+    // Las imágenes en punto flotante (.hdr, .exr) ya guardan radiancia lineal, que puede pasar de 1;
+    // se conserva tal cual en lugar de recortarla a 8 bits
+    fn from_hdr_image(img: &DynamicImage) -> Self {
+        let rgba = img.to_rgba32f();
+        let (width, height) = rgba.dimensions();
+        let data = rgba.pixels().map(|p| Radiance::new(p.0[0], p.0[1], p.0[2])).collect();
+        let alpha: Vec<f32> = rgba.pixels().map(|p| p.0[3].clamp(0.0, 1.0)).collect();
+        let alpha = if alpha.iter().any(|&a| a < 1.0) { Some(alpha) } else { None };
+        Self::from_pixels(width as usize, height as usize, data, alpha, ColorSpace::Linear)
+    }
+
+    // Crear una textura a partir de texels lineales ya decodificados y generar sus mipmaps
+    pub fn from_pixels(
+        width: usize,
+        height: usize,
+        data: Vec<Radiance>,
+        alpha: Option<Vec<f32>>,
+        color_space: ColorSpace,
+    ) -> Self {
+        assert_eq!(data.len(), width * height, "la textura debe tener width * height texels");
+        let mut texture = Texture {
             data,
+            alpha,
+            width,
+            height,
+            color_space,
+            mips: Vec::new(),
+        };
+        texture.generate_mipmaps();
+        texture
+    }
+
+    // Cadena de mipmaps con filtro de caja 2x2 hasta llegar a 1x1
+    fn generate_mipmaps(&mut self) {
+        self.mips.clear();
+        let (mut width, mut height) = (self.width, self.height);
+        while width > 1 || height > 1 {
+            let (data, alpha) = match self.mips.last() {
+                Some(level) => (&level.data, &level.alpha),
+                None => (&self.data, &self.alpha),
+            };
+            let level = downsample(width, height, data, alpha.as_deref());
+            width = level.width;
+            height = level.height;
+            self.mips.push(level);
         }
     }
 
-    // Método para obtener el ancho de la textura
-    pub fn width(&self) -> usize {
-        self.width
+    // Número de niveles, contando el nivel base
+    pub fn mip_levels(&self) -> usize {
+        self.mips.len() + 1
     }
 
-    // Método para obtener la altura de la textura
-    pub fn height(&self) -> usize {
-        self.height
+    // Nivel de detalle para un píxel que cubre `footprint` unidades UV de la superficie
+    pub fn lod(&self, footprint: f32) -> f32 {
+        self.full_region().lod(footprint)
     }
 
-    // Método para obtener el color de un píxel en una posición (x, y)
-    pub fn get_pixel(&self, x: usize, y: usize) -> Color {
+    // Método para obtener el color (lineal) de un píxel en una posición (x, y)
+    pub fn get_pixel(&self, x: usize, y: usize) -> Radiance {
         self.data[y * self.width + x]
     }
 
-    pub fn get_color(&self, u: f32, v: f32) -> Color {
-        let x = (u * (self.width - 1) as f32) as usize;
-        let y = (v * (self.height - 1) as f32) as usize;
-        self.get_pixel(x, y)
+    pub fn get_color(&self, u: f32, v: f32) -> Radiance {
+        self.sample(u, v, TextureFilter::Nearest, 0.0)
+    }
+
+    // Color (lineal) en las coordenadas UV con el filtro indicado; `lod` solo lo usa el trilineal
+    pub fn sample(&self, u: f32, v: f32, filter: TextureFilter, lod: f32) -> Radiance {
+        self.sample_region(u, v, &self.full_region(), filter, lod)
+    }
+
+    // Opacidad filtrada igual que `sample`
+    pub fn sample_alpha(&self, u: f32, v: f32, filter: TextureFilter, lod: f32) -> f32 {
+        self.sample_alpha_region(u, v, &self.full_region(), filter, lod)
+    }
+
+    // Como `sample`, pero las UV recorren solo `region` (p. ej. un bloque dentro de un atlas)
+    pub fn sample_region(&self, u: f32, v: f32, region: &TextureRegion, filter: TextureFilter, lod: f32) -> Radiance {
+        self.filtered(u, v, region, filter, lod, |level| level.2)
+    }
+
+    pub fn sample_alpha_region(&self, u: f32, v: f32, region: &TextureRegion, filter: TextureFilter, lod: f32) -> f32 {
+        match self.alpha {
+            Some(_) => self.filtered(u, v, region, filter, lod, |level| level.3.unwrap_or(&[])),
+            None => 1.0,
+        }
+    }
+
+    // Región que cubre toda la textura
+    pub fn full_region(&self) -> TextureRegion {
+        TextureRegion::new(0, 0, self.width, self.height)
+    }
+
+    fn filtered<T>(
+        &self,
+        u: f32,
+        v: f32,
+        region: &TextureRegion,
+        filter: TextureFilter,
+        lod: f32,
+        channel: impl Fn(Level<'_>) -> &[T],
+    ) -> T
+    where
+        T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let sample_level = |index: usize| {
+            let level = self.level(index);
+            bilinear(level.0, &self.region_at(region, index), channel(level), u, v)
+        };
+
+        match filter {
+            TextureFilter::Nearest => nearest(self.width, region, channel(self.level(0)), u, v),
+            TextureFilter::Bilinear => sample_level(0),
+            TextureFilter::Trilinear => {
+                let max_lod = self.max_lod(region);
+                let lod = lod.clamp(0.0, max_lod as f32);
+                let base = lod.floor() as usize;
+                let fine = sample_level(base);
+                let t = lod - base as f32;
+                if t <= 0.0 || base >= max_lod {
+                    return fine;
+                }
+                fine * (1.0 - t) + sample_level(base + 1) * t
+            }
+        }
+    }
+
+    fn level(&self, index: usize) -> Level<'_> {
+        if index == 0 {
+            (self.width, self.height, &self.data, self.alpha.as_deref())
+        } else {
+            let level = &self.mips[index - 1];
+            (level.width, level.height, &level.data, level.alpha.as_deref())
+        }
+    }
+
+    // Último nivel de mipmap en el que la región no se mezcla con texels vecinos:
+    // sus bordes tienen que caer sobre texels enteros del nivel
+    fn max_lod(&self, region: &TextureRegion) -> usize {
+        let last = self.mip_levels() - 1;
+        if *region == self.full_region() {
+            return last;
+        }
+        let aligned = (region.x | region.y | region.width | region.height).trailing_zeros() as usize;
+        aligned.min(last)
+    }
+
+    // Región equivalente en el nivel `index`
+    fn region_at(&self, region: &TextureRegion, index: usize) -> TextureRegion {
+        if *region == self.full_region() {
+            let (width, height, ..) = self.level(index);
+            return TextureRegion::new(0, 0, width, height);
+        }
+        TextureRegion::new(
+            region.x >> index,
+            region.y >> index,
+            (region.width >> index).max(1),
+            (region.height >> index).max(1),
+        )
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.alpha.is_some()
+    }
+
+    // Opacidad de un píxel; 1.0 si la textura no tiene canal alfa
+    pub fn get_alpha_pixel(&self, x: usize, y: usize) -> f32 {
+        match &self.alpha {
+            Some(alpha) => alpha[y * self.width + x],
+            None => 1.0,
+        }
+    }
+
+    // Opacidad en las coordenadas UV, con el mismo texel que `get_color`
+    pub fn get_alpha(&self, u: f32, v: f32) -> f32 {
+        self.sample_alpha(u, v, TextureFilter::Nearest, 0.0)
+    }
+
+    // Volver a codificar la textura en su espacio de color original para mostrarla o guardarla
+    pub fn to_rgb_image(&self) -> RgbImage {
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let texel = self.get_pixel(x as usize, y as usize);
+            let color = match self.color_space {
+                ColorSpace::Srgb => texel.to_srgb(),
+                ColorSpace::Linear => {
+                    let encode = |c: f32| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+                    Color::new(encode(texel.r), encode(texel.g), encode(texel.b))
+                }
+            };
+            image::Rgb([color.r, color.g, color.b])
+        })
+    }
+
+    // Igual que `to_rgb_image`, conservando el canal alfa
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        let rgb = self.to_rgb_image();
+        RgbaImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let [r, g, b] = rgb.get_pixel(x, y).0;
+            let a = (self.get_alpha_pixel(x as usize, y as usize) * 255.0 + 0.5) as u8;
+            image::Rgba([r, g, b, a])
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        if self.has_alpha() {
+            self.to_rgba_image().save(path)
+        } else {
+            self.to_rgb_image().save(path)
+        }
+    }
+}
+
+// Ancho, alto, colores y alfa de un nivel de mipmap
+type Level<'a> = (usize, usize, &'a [Radiance], Option<&'a [f32]>);
+
+// Texel de la región que contiene el punto UV; los bordes se repiten en lugar de envolver
+// para que no se mezclen lados opuestos de la cara
+fn nearest<T: Copy>(stride: usize, region: &TextureRegion, data: &[T], u: f32, v: f32) -> T {
+    let x = ((u * region.width as f32) as usize).min(region.width - 1);
+    let y = ((v * region.height as f32) as usize).min(region.height - 1);
+    data[(region.y + y) * stride + region.x + x]
+}
+
+fn bilinear<T>(stride: usize, region: &TextureRegion, data: &[T], u: f32, v: f32) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+{
+    // Centros de los texels en (i + 0.5) / width
+    let x = (u * region.width as f32 - 0.5).clamp(0.0, (region.width - 1) as f32);
+    let y = (v * region.height as f32 - 0.5).clamp(0.0, (region.height - 1) as f32);
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(region.width - 1), (y0 + 1).min(region.height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let texel = |x: usize, y: usize| data[(region.y + y) * stride + region.x + x];
+    let top = texel(x0, y0) * (1.0 - tx) + texel(x1, y0) * tx;
+    let bottom = texel(x0, y1) * (1.0 - tx) + texel(x1, y1) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+// Reducir un nivel a la mitad promediando bloques de 2x2 texels. Con un lado impar, la última
+// fila o columna se suma al bloque anterior (3 texels) en lugar de perderse.
+// Con alfa, el color se pondera por la opacidad para que los texels transparentes
+// (normalmente negros) no oscurezcan los bordes de las hojas a lo lejos
+fn downsample(width: usize, height: usize, data: &[Radiance], alpha: Option<&[f32]>) -> MipLevel {
+    let (new_width, new_height) = ((width / 2).max(1), (height / 2).max(1));
+    let mut new_data = Vec::with_capacity(new_width * new_height);
+    let mut new_alpha = Vec::with_capacity(if alpha.is_some() { new_width * new_height } else { 0 });
+
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let mut color = Radiance::BLACK;
+            let mut weighted = Radiance::BLACK;
+            let mut coverage = 0.0;
+            let mut count = 0.0;
+            for sy in source_range(y, new_height, height) {
+                for sx in source_range(x, new_width, width) {
+                    let i = sy * width + sx;
+                    let a = alpha.map_or(1.0, |alpha| alpha[i]);
+                    color += data[i];
+                    weighted += data[i] * a;
+                    coverage += a;
+                    count += 1.0;
+                }
+            }
+            new_data.push(if coverage > 0.0 { weighted * (1.0 / coverage) } else { color * (1.0 / count) });
+            if alpha.is_some() {
+                new_alpha.push(coverage / count);
+            }
+        }
+    }
+
+    MipLevel {
+        width: new_width,
+        height: new_height,
+        data: new_data,
+        alpha: alpha.map(|_| new_alpha),
+    }
+}
+
+// Texels del nivel anterior que caen en el texel `i` de un lado reducido de `size` a `new_size`
+fn source_range(i: usize, new_size: usize, size: usize) -> std::ops::Range<usize> {
+    let end = if i + 1 == new_size { size } else { i * 2 + 2 };
+    (i * 2)..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::ray_intersect::{Material, RayIntersect};
+    use nalgebra_glm::Vec3;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    // PNG de 2x1 en el directorio temporal con los píxeles indicados
+    fn png(name: &str, pixels: [[u8; 3]; 2]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("diorama-texture-{}-{}.png", std::process::id(), name));
+        RgbImage::from_fn(2, 1, |x, _| image::Rgb(pixels[x as usize])).save(&path).unwrap();
+        path
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn color_textures_are_decoded_from_srgb() {
+        let path = png("srgb", [[128, 0, 255], [188, 188, 188]]);
+        let texture = Texture::load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(texture.color_space, ColorSpace::Srgb);
+        let texel = texture.get_pixel(0, 0);
+        // sRGB 128 es ~21.6 % de la luz, no la mitad
+        assert!(close(texel.r, 0.2158), "{:?}", texel);
+        assert_eq!(texel.g, 0.0);
+        assert_eq!(texel.b, 1.0);
+        assert!(close(texture.get_pixel(1, 0).r, 0.5029));
+    }
+
+    #[test]
+    fn data_textures_are_kept_linear() {
+        let path = png("lineal", [[128, 0, 255], [188, 188, 188]]);
+        let texture = Texture::load_linear(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(texture.color_space, ColorSpace::Linear);
+        assert!(close(texture.get_pixel(0, 0).r, 128.0 / 255.0));
+        assert!(close(texture.get_pixel(1, 0).g, 188.0 / 255.0));
+    }
+
+    // PNG RGBA de 3x1 con opacidades 0, 128 y 255
+    fn rgba_png(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("diorama-texture-{}-{}.png", std::process::id(), name));
+        RgbaImage::from_fn(3, 1, |x, _| image::Rgba([200, 100, 50, [0, 128, 255][x as usize]])).save(&path).unwrap();
+        path
+    }
+
+    fn textured(texture: Texture) -> Material {
+        Material::new(Color::new(255, 255, 255), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0, true, Some(Arc::new(texture)))
+    }
+
+    #[test]
+    fn alpha_is_kept_only_when_the_image_has_transparency() {
+        let path = rgba_png("alfa");
+        let texture = Texture::load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(texture.has_alpha());
+        assert_eq!(texture.get_alpha_pixel(0, 0), 0.0);
+        assert!(close(texture.get_alpha_pixel(1, 0), 128.0 / 255.0));
+        assert_eq!(texture.get_alpha_pixel(2, 0), 1.0);
+        // El alfa no se decodifica como sRGB
+        assert_eq!(texture.to_rgba_image().get_pixel(1, 0).0[3], 128);
+
+        let path = png("opaca", [[1, 2, 3], [4, 5, 6]]);
+        let texture = Texture::load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!texture.has_alpha());
+        assert_eq!(texture.get_alpha(0.5, 0.5), 1.0);
+    }
+
+    #[test]
+    fn cutout_depends_on_the_alpha_cutoff() {
+        let path = rgba_png("recorte");
+        let texture = Texture::load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // u = 0, 0.5 y 1 caen en los texels 0, 1 y 2
+        let material = textured(texture);
+        assert!(material.is_cutout(0.0, 0.0));
+        assert!(!material.is_cutout(0.5, 0.0), "el alfa parcial se mezcla, no se recorta");
+        assert!(!material.is_cutout(1.0, 0.0));
+
+        let material = material.with_alpha_cutoff(0.6);
+        assert!(material.is_cutout(0.5, 0.0));
+        assert!(!material.is_cutout(1.0, 0.0));
+
+        // Sin textura no hay nada que recortar
+        let plain = Material::new(Color::new(255, 255, 255), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0, false, None);
+        assert!(!plain.with_alpha_cutoff(1.0).is_cutout(0.0, 0.0));
+    }
+
+    #[test]
+    fn cut_out_cubes_let_rays_through_and_blended_ones_do_not() {
+        let single = |alpha: u8| {
+            let path = std::env::temp_dir().join(format!("diorama-texture-{}-cubo-{}.png", std::process::id(), alpha));
+            RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, alpha])).save(&path).unwrap();
+            let texture = Texture::load_from_file(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            Arc::new(textured(texture))
+        };
+        let origin = Vec3::new(0.0, 0.0, -5.0);
+        let direction = Vec3::new(0.0, 0.0, 1.0);
+
+        let hojas = Cube::new(Vec3::zeros(), 1.0, std::array::from_fn(|_| single(0)));
+        assert!(!hojas.ray_intersect(&origin, &direction).is_intersecting);
+
+        let vidrio = Cube::new(Vec3::zeros(), 1.0, std::array::from_fn(|_| single(128)));
+        let hit = vidrio.ray_intersect(&origin, &direction);
+        assert!(hit.is_intersecting);
+        assert!((hit.distance - 4.5).abs() < 1e-5, "debe chocar con la cara de entrada");
+        assert!(hit.material.get_alpha(hit.u, hit.v) < 1.0);
+    }
+
+    // Textura de grises (valor = r = g = b) sin alfa
+    fn gray(width: usize, height: usize, values: &[f32]) -> Texture {
+        let data = values.iter().map(|&v| Radiance::new(v, v, v)).collect();
+        Texture::from_pixels(width, height, data, None, ColorSpace::Linear)
+    }
+
+    #[test]
+    fn nearest_picks_the_texel_under_the_point() {
+        let texture = gray(2, 1, &[0.0, 1.0]);
+        assert_eq!(texture.sample(0.25, 0.5, TextureFilter::Nearest, 0.0).r, 0.0);
+        assert_eq!(texture.sample(0.49, 0.5, TextureFilter::Nearest, 0.0).r, 0.0);
+        assert_eq!(texture.sample(0.51, 0.5, TextureFilter::Nearest, 0.0).r, 1.0);
+        // El borde no envuelve al lado opuesto
+        assert_eq!(texture.sample(1.0, 1.0, TextureFilter::Nearest, 0.0).r, 1.0);
+    }
+
+    #[test]
+    fn bilinear_blends_between_texel_centers() {
+        let texture = gray(2, 1, &[0.0, 1.0]);
+        // Los centros de los texels están en u = 0.25 y 0.75
+        assert_eq!(texture.sample(0.25, 0.5, TextureFilter::Bilinear, 0.0).r, 0.0);
+        assert!(close(texture.sample(0.5, 0.5, TextureFilter::Bilinear, 0.0).r, 0.5));
+        assert!(close(texture.sample(0.625, 0.5, TextureFilter::Bilinear, 0.0).r, 0.75));
+        // Fuera de los centros se repite el borde
+        assert_eq!(texture.sample(0.0, 0.5, TextureFilter::Bilinear, 0.0).r, 0.0);
+        assert_eq!(texture.sample(1.0, 0.5, TextureFilter::Bilinear, 0.0).r, 1.0);
+        // El bilineal ignora el nivel de detalle
+        assert_eq!(texture.sample(0.25, 0.5, TextureFilter::Bilinear, 5.0).r, 0.0);
+    }
+
+    #[test]
+    fn trilinear_blends_between_mip_levels() {
+        // Tablero de 2x2: el nivel 1 es un solo texel gris
+        let texture = gray(2, 2, &[0.0, 1.0, 1.0, 0.0]);
+        assert_eq!(texture.mip_levels(), 2);
+        let at = |lod: f32| texture.sample(0.25, 0.25, TextureFilter::Trilinear, lod).r;
+        assert_eq!(at(0.0), 0.0);
+        assert!(close(at(0.5), 0.25));
+        assert!(close(at(1.0), 0.5));
+        // Más allá del último nivel se queda en él
+        assert!(close(at(7.0), 0.5));
+    }
+
+    #[test]
+    fn mipmaps_halve_down_to_one_texel() {
+        let texture = gray(8, 2, &[0.5; 16]);
+        let sizes: Vec<(usize, usize)> = (0..texture.mip_levels())
+            .map(|i| {
+                let (width, height, ..) = texture.level(i);
+                (width, height)
+            })
+            .collect();
+        assert_eq!(sizes, vec![(8, 2), (4, 1), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn odd_sizes_fold_the_last_texels_into_the_box_filter() {
+        // Con 5 columnas, la última va al segundo texel del nivel 1 en lugar de perderse
+        let texture = gray(5, 1, &[1.0, 1.0, 1.0, 1.0, 4.0]);
+        let (width, height, data, _) = texture.level(1);
+        assert_eq!((width, height), (2, 1));
+        assert!(close(data[0].r, 1.0));
+        assert!(close(data[1].r, 2.0));
+
+        // 3x3: la última fila y la última columna cuentan en el único texel del nivel 1
+        let texture = gray(3, 3, &[0.0, 0.0, 9.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        let (width, height, data, _) = texture.level(1);
+        assert_eq!((width, height), (1, 1));
+        assert!(close(data[0].r, 1.0));
+    }
+
+    #[test]
+    fn lod_is_the_log2_of_the_texels_per_pixel() {
+        let texture = gray(16, 16, &[0.0; 256]);
+        assert_eq!(texture.lod(0.0), 0.0);
+        // Un píxel que cubre menos de un texel usa el nivel base
+        assert_eq!(texture.lod(1.0 / 32.0), 0.0);
+        assert!(close(texture.lod(1.0 / 16.0), 0.0));
+        assert!(close(texture.lod(0.25), 2.0));
+        assert!(close(texture.lod(1.0), 4.0));
+    }
+
+    #[test]
+    fn encoding_back_gives_the_original_pixels() {
+        let pixels = [[128, 7, 255], [1, 188, 64]];
+        for linear in [false, true] {
+            let path = png(if linear { "ida-lineal" } else { "ida-srgb" }, pixels);
+            let texture = if linear {
+                Texture::load_linear(&path).unwrap()
+            } else {
+                Texture::load_from_file(&path).unwrap()
+            };
+            std::fs::remove_file(&path).unwrap();
+
+            let image = texture.to_rgb_image();
+            assert_eq!(image.get_pixel(0, 0).0, pixels[0]);
+            assert_eq!(image.get_pixel(1, 0).0, pixels[1]);
+        }
+    }
+    #[test]
+    fn load_errors_are_classified_by_cause() {
+        let dir = std::env::temp_dir();
+        let file = |name: &str, bytes: &[u8]| {
+            let path = dir.join(format!("diorama-texture-{}-{}", std::process::id(), name));
+            std::fs::write(&path, bytes).unwrap();
+            path
+        };
+        let truncated = {
+            let path = png("truncada", [[1, 2, 3], [4, 5, 6]]);
+            let bytes = std::fs::read(&path).unwrap();
+            std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+            path
+        };
+        // PNG válido de 0x16 píxeles
+        let empty: [u8; 65] = [
+            137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 0, 0, 0, 0, 16, 8, 6, 0, 0, 0, 56,
+            93, 126, 137, 0, 0, 0, 8, 73, 68, 65, 84, 120, 156, 3, 0, 0, 0, 0, 1, 72, 6, 137, 210, 0, 0, 0, 0, 73, 69,
+            78, 68, 174, 66, 96, 130,
+        ];
+        let paths = [file("basura.png", b"no es un png"), file("formato.xyz", b"???"), file("vacia.png", &empty), truncated];
+
+        let missing = dir.join("diorama-no-existe.png");
+        assert!(matches!(Texture::load_from_file(&missing), Err(TextureError::NotFound { path }) if path == missing));
+        assert!(matches!(Texture::load_from_file(&paths[0]), Err(TextureError::Decode { .. })));
+        assert!(matches!(Texture::load_from_file(&paths[1]), Err(TextureError::UnsupportedFormat { .. })));
+        assert!(matches!(Texture::load_from_file(&paths[2]), Err(TextureError::Decode { .. })));
+        assert!(matches!(Texture::load_from_file(&paths[3]), Err(TextureError::Decode { .. })));
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn placeholder_is_a_magenta_and_black_checkerboard() {
+        let texture = Texture::placeholder();
+        assert_eq!((texture.width, texture.height), (16, 16));
+        assert!(!texture.has_alpha());
+        let magenta = Color::new(255, 0, 255);
+        let black = Color::new(0, 0, 0);
+        for (x, y, expected) in [(0, 0, magenta), (7, 7, magenta), (8, 0, black), (0, 8, black), (15, 15, magenta)] {
+            assert_eq!(texture.get_pixel(x, y).to_srgb(), expected, "({}, {})", x, y);
+        }
     }
 }
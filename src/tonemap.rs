@@ -0,0 +1,85 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::radiance::Radiance;
+
+// Operador para llevar la radiancia HDR al rango de la pantalla
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapping {
+    Clamp,      // Recortar a 1.0, como el pipeline de 8 bits original
+    Reinhard,   // L / (1 + L) sobre la luminancia
+    AcesFilmic, // Aproximación de la curva ACES de Narkowicz
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "clamp" | "none" => Ok(ToneMapping::Clamp),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "aces" | "filmic" => Ok(ToneMapping::AcesFilmic),
+            _ => Err(format!("operador de tone mapping desconocido: {} (clamp, reinhard, aces)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ToneMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ToneMapping::Clamp => "clamp",
+            ToneMapping::Reinhard => "reinhard",
+            ToneMapping::AcesFilmic => "aces",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Etapa final del render: exposición, operador y codificación sRGB
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapper {
+    pub operator: ToneMapping,
+    pub exposure: f32, // Multiplicador lineal aplicado antes del operador
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        ToneMapper {
+            operator: ToneMapping::AcesFilmic,
+            exposure: 1.0,
+        }
+    }
+}
+
+impl ToneMapper {
+    pub fn new(operator: ToneMapping, exposure: f32) -> Self {
+        ToneMapper { operator, exposure }
+    }
+
+    // Radiancia lineal en el rango 0..1 lista para codificar
+    pub fn apply(&self, radiance: Radiance) -> Radiance {
+        let exposed = radiance.scale(self.exposure);
+        match self.operator {
+            ToneMapping::Clamp => exposed,
+            ToneMapping::Reinhard => {
+                let luminance = exposed.luminance();
+                if luminance <= 0.0 {
+                    Radiance::BLACK
+                } else {
+                    exposed.scale(1.0 / (1.0 + luminance))
+                }
+            }
+            ToneMapping::AcesFilmic => {
+                let aces = |x: f32| {
+                    let x = x.max(0.0);
+                    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+                };
+                Radiance::new(aces(exposed.r), aces(exposed.g), aces(exposed.b))
+            }
+        }
+    }
+
+    // Píxel 0xRRGGBB en sRGB para el framebuffer
+    pub fn to_pixel(&self, radiance: Radiance) -> u32 {
+        self.apply(radiance).to_srgb().to_u32()
+    }
+}
@@ -0,0 +1,434 @@
+use nalgebra_glm::Vec3;
+use std::any::Any;
+use std::sync::Arc;
+use crate::bvh::Aabb;
+use crate::cube::face_uv;
+use crate::ray_intersect::{Intersect, Material, RayIntersect};
+
+// Lado de un chunk en bloques
+const CHUNK_SIZE: usize = 16;
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+// Identificador de un tipo de bloque dentro de la paleta de la cuadrícula; 0 es aire
+pub type BlockId = u16;
+pub const AIR: BlockId = 0;
+
+// Mundo de bloques guardado como una cuadrícula de chunks de 16x16x16.
+// Los rayos la recorren celda por celda con el algoritmo DDA de Amanatides & Woo,
+// así que el costo depende del camino del rayo y no del número de bloques.
+pub struct VoxelGrid {
+    pub origin: Vec3,      // Esquina mínima de la cuadrícula
+    pub block_size: f32,   // Lado de cada bloque
+    dims: [usize; 3],      // Tamaño en bloques
+    chunk_dims: [usize; 3],
+    chunks: Vec<Option<Box<[BlockId; CHUNK_VOLUME]>>>, // `None` = chunk vacío
+    palette: Vec<[Arc<Material>; 6]>,                      // Materiales por cara de cada tipo de bloque
+}
+
+impl VoxelGrid {
+    pub fn new(origin: Vec3, dims: [usize; 3], block_size: f32) -> Self {
+        let chunk_dims = Self::chunk_dims(dims);
+        VoxelGrid {
+            origin,
+            block_size,
+            dims,
+            chunk_dims,
+            chunks: vec![None; chunk_dims[0] * chunk_dims[1] * chunk_dims[2]],
+            palette: Vec::new(),
+        }
+    }
+
+    // Chunks por eje para una cuadrícula de `dims` bloques
+    fn chunk_dims(dims: [usize; 3]) -> [usize; 3] {
+        dims.map(|d| d.div_ceil(CHUNK_SIZE))
+    }
+
+    // Chunks que reserva una cuadrícula de `dims` bloques, o `None` si no caben en un usize
+    pub fn chunk_count(dims: [usize; 3]) -> Option<usize> {
+        Self::chunk_dims(dims).iter().try_fold(1usize, |acc, &d| acc.checked_mul(d))
+    }
+
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    // Registrar un tipo de bloque y obtener su identificador
+    pub fn add_block_type(&mut self, materials: [Arc<Material>; 6]) -> BlockId {
+        assert!(
+            self.palette.len() < BlockId::MAX as usize,
+            "la paleta admite a lo sumo {} tipos de bloque",
+            BlockId::MAX
+        );
+        self.palette.push(materials);
+        self.palette.len() as BlockId
+    }
+
+    pub fn block_materials(&self, id: BlockId) -> Option<&[Arc<Material>; 6]> {
+        if id == AIR {
+            None
+        } else {
+            self.palette.get(id as usize - 1)
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> BlockId {
+        if x >= self.dims[0] || y >= self.dims[1] || z >= self.dims[2] {
+            return AIR;
+        }
+        let (chunk, offset) = self.locate(x, y, z);
+        match &self.chunks[chunk] {
+            Some(blocks) => blocks[offset],
+            None => AIR,
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, id: BlockId) {
+        assert!(
+            x < self.dims[0] && y < self.dims[1] && z < self.dims[2],
+            "bloque ({}, {}, {}) fuera de la cuadrícula {:?}",
+            x,
+            y,
+            z,
+            self.dims
+        );
+        assert!(id as usize <= self.palette.len(), "tipo de bloque desconocido {}", id);
+
+        let (chunk, offset) = self.locate(x, y, z);
+        match &mut self.chunks[chunk] {
+            Some(blocks) => blocks[offset] = id,
+            None if id == AIR => {}
+            slot => {
+                let mut blocks = Box::new([AIR; CHUNK_VOLUME]);
+                blocks[offset] = id;
+                *slot = Some(blocks);
+            }
+        }
+    }
+
+    // Celdas ocupadas y su tipo de bloque, saltando los chunks vacíos
+    pub fn blocks(&self) -> impl Iterator<Item = ([usize; 3], BlockId)> + '_ {
+        let [cx, _, cz] = self.chunk_dims;
+        self.chunks.iter().enumerate().flat_map(move |(chunk, blocks)| {
+            let base = [chunk % cx * CHUNK_SIZE, chunk / (cx * cz) * CHUNK_SIZE, chunk / cx % cz * CHUNK_SIZE];
+            blocks.iter().flat_map(move |blocks| {
+                blocks.iter().enumerate().filter(|(_, &id)| id != AIR).filter_map(move |(offset, &id)| {
+                    let cell = [
+                        base[0] + offset % CHUNK_SIZE,
+                        base[1] + offset / (CHUNK_SIZE * CHUNK_SIZE),
+                        base[2] + offset / CHUNK_SIZE % CHUNK_SIZE,
+                    ];
+                    (cell[0] < self.dims[0] && cell[1] < self.dims[1] && cell[2] < self.dims[2]).then_some((cell, id))
+                })
+            })
+        })
+    }
+
+    // Celda que contiene el punto, si está dentro de la cuadrícula
+    pub fn cell_at(&self, point: &Vec3) -> Option<[usize; 3]> {
+        let local = (point - self.origin) / self.block_size;
+        let mut cell = [0; 3];
+        for axis in 0..3 {
+            let c = local[axis].floor();
+            if c < 0.0 || c >= self.dims[axis] as f32 {
+                return None;
+            }
+            cell[axis] = c as usize;
+        }
+        Some(cell)
+    }
+
+    // Esquina mínima de una celda
+    pub fn cell_min(&self, cell: [usize; 3]) -> Vec3 {
+        self.origin + Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32) * self.block_size
+    }
+
+    fn locate(&self, x: usize, y: usize, z: usize) -> (usize, usize) {
+        let (cx, cy, cz) = (x / CHUNK_SIZE, y / CHUNK_SIZE, z / CHUNK_SIZE);
+        let (ox, oy, oz) = (x % CHUNK_SIZE, y % CHUNK_SIZE, z % CHUNK_SIZE);
+        let chunk = (cy * self.chunk_dims[2] + cz) * self.chunk_dims[0] + cx;
+        let offset = (oy * CHUNK_SIZE + oz) * CHUNK_SIZE + ox;
+        (chunk, offset)
+    }
+
+    fn hit(&self, cell: [usize; 3], id: BlockId, point: Vec3, distance: f32, face_index: usize) -> Intersect<'_> {
+        let min = self.cell_min(cell);
+        let max = min + Vec3::new(self.block_size, self.block_size, self.block_size);
+
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+        normal[face_index / 2] = if face_index.is_multiple_of(2) { -1.0 } else { 1.0 };
+
+        let (u, v) = face_uv(face_index, &point, &min, &max);
+        let materials = &self.palette[id as usize - 1];
+        Intersect::new(
+            point,
+            normal,
+            distance,
+            &materials[face_index],
+            u.clamp(0.0, 1.0),
+            v.clamp(0.0, 1.0),
+        )
+        .with_uv_scale(1.0 / self.block_size)
+    }
+
+    // Recorrido DDA hasta la distancia `max_t`. Con `skip_start`, el bloque que contiene
+    // el origen se ignora por completo (rayos de sombra que salen desde dentro de un bloque);
+    // sin él, se ve su cara de salida
+    fn cast(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_t: f32, skip_start: bool) -> Intersect<'_> {
+        let bounds = self.bounding_box();
+        let inv_dir = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+
+        // Recortamos el rayo contra la caja de la cuadrícula
+        let t0 = (bounds.min - ray_origin).component_mul(&inv_dir);
+        let t1 = (bounds.max - ray_origin).component_mul(&inv_dir);
+        let mut t_enter = f32::NEG_INFINITY;
+        let mut enter_axis = 0;
+        for axis in 0..3 {
+            let t_near = t0[axis].min(t1[axis]);
+            if t_near > t_enter {
+                t_enter = t_near;
+                enter_axis = axis;
+            }
+        }
+        let t_exit = t0.x.max(t1.x).min(t0.y.max(t1.y)).min(t0.z.max(t1.z)).min(max_t);
+        if t_enter > t_exit || t_exit < 0.0 {
+            return Intersect::empty();
+        }
+
+        // Si el origen está dentro de la cuadrícula no hay cara de entrada
+        let starts_inside = t_enter < 0.0;
+        let mut t = t_enter.max(0.0);
+
+        let start = ray_origin + ray_direction * t;
+        let local = (start - self.origin) / self.block_size;
+        let mut cell = [0i64; 3];
+        let mut step = [0i64; 3];
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            cell[axis] = (local[axis].floor() as i64).clamp(0, self.dims[axis] as i64 - 1);
+            if ray_direction[axis] > 0.0 {
+                step[axis] = 1;
+                let boundary = self.origin[axis] + (cell[axis] + 1) as f32 * self.block_size;
+                t_max[axis] = (boundary - ray_origin[axis]) * inv_dir[axis];
+                t_delta[axis] = self.block_size * inv_dir[axis];
+            } else if ray_direction[axis] < 0.0 {
+                step[axis] = -1;
+                let boundary = self.origin[axis] + cell[axis] as f32 * self.block_size;
+                t_max[axis] = (boundary - ray_origin[axis]) * inv_dir[axis];
+                t_delta[axis] = -self.block_size * inv_dir[axis];
+            }
+        }
+
+        let face_for = |axis: usize, step: i64| axis * 2 + if step > 0 { 0 } else { 1 };
+        let mut face_index = face_for(enter_axis, step[enter_axis]);
+        let mut first = true;
+
+        // Eje cuyo borde de celda está más cerca
+        let next_axis = |t_max: &[f32; 3]| {
+            if t_max[0] < t_max[1] {
+                if t_max[0] < t_max[2] { 0 } else { 2 }
+            } else if t_max[1] < t_max[2] {
+                1
+            } else {
+                2
+            }
+        };
+
+        loop {
+            let ucell = [cell[0] as usize, cell[1] as usize, cell[2] as usize];
+            let id = self.get(ucell[0], ucell[1], ucell[2]);
+            if id != AIR && !(first && starts_inside && skip_start) {
+                // Si el origen está dentro de un bloque no hay cara de entrada
+                if !(first && starts_inside) {
+                    let point = ray_origin + ray_direction * t;
+                    let entrada = self.hit(ucell, id, point, t, face_index);
+                    if !entrada.material.is_cutout(entrada.u, entrada.v) {
+                        return entrada;
+                    }
+                }
+
+                // Como `Cube`, probamos la cara de salida: el origen está dentro del bloque
+                // o la cara de entrada es transparente en ese texel
+                let axis = next_axis(&t_max);
+                let t_out = t_max[axis];
+                let point = ray_origin + ray_direction * t_out;
+                let exit_face = axis * 2 + if step[axis] > 0 { 1 } else { 0 };
+                let salida = self.hit(ucell, id, point, t_out, exit_face);
+                if !salida.material.is_cutout(salida.u, salida.v) {
+                    return salida;
+                }
+            }
+            first = false;
+
+            // Avanzamos a la siguiente celda
+            let axis = next_axis(&t_max);
+
+            t = t_max[axis];
+            if t > t_exit {
+                return Intersect::empty();
+            }
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= self.dims[axis] as i64 {
+                return Intersect::empty();
+            }
+            t_max[axis] += t_delta[axis];
+            face_index = face_for(axis, step[axis]);
+        }
+    }
+}
+
+impl RayIntersect for VoxelGrid {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect<'_> {
+        self.cast(ray_origin, ray_direction, f32::INFINITY, false)
+    }
+
+    // El recorrido se corta en `max_t`, así que una luz cercana no recorre toda la cuadrícula
+    fn occludes(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_t: f32) -> bool {
+        let intersect = self.cast(ray_origin, ray_direction, max_t, true);
+        intersect.is_intersecting && intersect.distance < max_t
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3::new(self.dims[0] as f32, self.dims[1] as f32, self.dims[2] as f32) * self.block_size;
+        Aabb::new(self.origin, self.origin + extent)
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::cube::Cube;
+
+    // Un material distinto por cara, para saber cuál cara se vio
+    fn faces() -> [Arc<Material>; 6] {
+        std::array::from_fn(|i| {
+            Arc::new(Material::new(Color::new(40 * i as u8, 0, 0), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0, false, None))
+        })
+    }
+
+    // Cuadrícula de 4x4x4 bloques de lado 0.5 con su esquina en (-1, -1, -1) y dos bloques:
+    // uno en la celda (1, 1, 1) y otro en la (3, 1, 1)
+    fn grid() -> (VoxelGrid, [Arc<Material>; 6]) {
+        let faces = faces();
+        let mut grid = VoxelGrid::new(Vec3::new(-1.0, -1.0, -1.0), [4, 4, 4], 0.5);
+        let id = grid.add_block_type(faces.clone());
+        grid.set(1, 1, 1, id);
+        grid.set(3, 1, 1, id);
+        (grid, faces)
+    }
+
+    fn same_material(hit: &Intersect, faces: &[Arc<Material>; 6], face: usize) -> bool {
+        std::ptr::eq(hit.material, &*faces[face])
+    }
+
+    #[test]
+    fn hits_report_face_normal_distance_and_uv_like_a_cube() {
+        let (grid, faces) = grid();
+        // El bloque (1, 1, 1) ocupa [-0.5, 0]³: el mismo que un cubo de lado 0.5 con centro en -0.25
+        let cube = Cube::new(Vec3::new(-0.25, -0.25, -0.25), 0.5, faces.clone());
+        let rays = [
+            (Vec3::new(-0.4, 3.0, -0.1), Vec3::new(0.0, -1.0, 0.0), 3, Vec3::new(0.0, 1.0, 0.0)),
+            (Vec3::new(-0.1, -0.3, -3.0), Vec3::new(0.0, 0.0, 1.0), 4, Vec3::new(0.0, 0.0, -1.0)),
+            (Vec3::new(-3.0, -0.2, -0.4), Vec3::new(1.0, 0.0, 0.0), 0, Vec3::new(-1.0, 0.0, 0.0)),
+            (Vec3::new(-0.3, -3.0, -0.35), Vec3::new(0.0, 1.0, 0.0), 2, Vec3::new(0.0, -1.0, 0.0)),
+        ];
+        for (origin, direction, face, normal) in rays {
+            let hit = grid.ray_intersect(&origin, &direction);
+            let expected = cube.ray_intersect(&origin, &direction);
+            assert!(hit.is_intersecting, "el rayo desde {:?} no chocó", origin);
+            assert!(same_material(&hit, &faces, face), "cara equivocada desde {:?}", origin);
+            assert_eq!(hit.normal, normal);
+            assert!((hit.distance - expected.distance).abs() < 1e-5);
+            assert!((hit.point - expected.point).magnitude() < 1e-5);
+            assert!((hit.u - expected.u).abs() < 1e-5 && (hit.v - expected.v).abs() < 1e-5, "UV distintas desde {:?}", origin);
+            assert_eq!(hit.uv_scale, 2.0);
+        }
+    }
+
+    #[test]
+    fn oblique_rays_hit_the_first_block_on_their_path() {
+        let (grid, faces) = grid();
+        // Pasa por encima del bloque (1, 1, 1) y baja hasta la cara de arriba del (3, 1, 1)
+        let origin = Vec3::new(-0.25, 0.5, -0.25);
+        let target = Vec3::new(0.75, 0.0, -0.25);
+        let hit = grid.ray_intersect(&origin, &(target - origin).normalize());
+        assert!(hit.is_intersecting);
+        assert!(same_material(&hit, &faces, 3));
+        assert!((hit.point - target).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn rays_that_miss_or_point_away_do_not_hit() {
+        let (grid, _) = grid();
+        assert!(!grid.ray_intersect(&Vec3::new(-0.9, 3.0, 0.9), &Vec3::new(0.0, -1.0, 0.0)).is_intersecting);
+        assert!(!grid.ray_intersect(&Vec3::new(-0.25, 3.0, -0.25), &Vec3::new(0.0, 1.0, 0.0)).is_intersecting);
+        assert!(!grid.ray_intersect(&Vec3::new(5.0, 5.0, 5.0), &Vec3::new(1.0, 0.0, 0.0)).is_intersecting);
+    }
+
+    #[test]
+    fn rays_starting_inside_the_grid_in_air_hit_the_entry_face() {
+        let (grid, faces) = grid();
+        // Desde la celda (2, 1, 1), vacía, hacia +X: cara -X del bloque (3, 1, 1)
+        let hit = grid.ray_intersect(&Vec3::new(0.25, -0.25, -0.25), &Vec3::new(1.0, 0.0, 0.0));
+        assert!(hit.is_intersecting);
+        assert!(same_material(&hit, &faces, 0));
+        assert!((hit.distance - 0.25).abs() < 1e-5);
+        // Y hacia -X: cara +X del bloque (1, 1, 1)
+        let hit = grid.ray_intersect(&Vec3::new(0.25, -0.25, -0.25), &Vec3::new(-1.0, 0.0, 0.0));
+        assert!(same_material(&hit, &faces, 1));
+        assert_eq!(hit.normal, Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rays_starting_inside_a_block_see_its_exit_face() {
+        let (grid, faces) = grid();
+        let origin = Vec3::new(-0.25, -0.25, -0.25);
+        let hit = grid.ray_intersect(&origin, &Vec3::new(0.0, 1.0, 0.0));
+        assert!(hit.is_intersecting);
+        assert!(same_material(&hit, &faces, 3));
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!((hit.distance - 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "tipos de bloque")]
+    fn palette_overflow_panics_instead_of_wrapping() {
+        let mut grid = VoxelGrid::new(Vec3::zeros(), [1, 1, 1], 1.0);
+        let faces = faces();
+        for _ in 0..=BlockId::MAX as usize {
+            grid.add_block_type(faces.clone());
+        }
+    }
+
+    #[test]
+    fn shadow_rays_ignore_the_block_they_start_in() {
+        let (grid, _) = grid();
+        let origin = Vec3::new(-0.25, -0.25, -0.25);
+        // Hacia arriba no hay nada más
+        assert!(!grid.occludes(&origin, &Vec3::new(0.0, 1.0, 0.0), 10.0));
+        // Hacia +X está el bloque (3, 1, 1), pero solo si la luz queda detrás de él
+        assert!(grid.occludes(&origin, &Vec3::new(1.0, 0.0, 0.0), 10.0));
+        assert!(!grid.occludes(&origin, &Vec3::new(1.0, 0.0, 0.0), 0.5));
+    }
+
+    #[test]
+    fn chunk_count_rounds_up_and_detects_overflow() {
+        assert_eq!(VoxelGrid::chunk_count([16, 17, 1]), Some(2));
+        assert_eq!(VoxelGrid::chunk_count([usize::MAX, usize::MAX, 16]), None);
+    }
+
+    #[test]
+    fn blocks_lists_every_occupied_cell() {
+        let (grid, _) = grid();
+        let mut cells: Vec<[usize; 3]> = grid.blocks().map(|(cell, _)| cell).collect();
+        cells.sort();
+        assert_eq!(cells, vec![[1, 1, 1], [3, 1, 1]]);
+        assert_eq!(grid.cell_at(&Vec3::new(0.9, -0.6, -0.6)), Some([3, 0, 0]));
+        assert_eq!(grid.cell_at(&Vec3::new(1.1, 0.0, 0.0)), None);
+    }
+}
@@ -0,0 +1,79 @@
+// Luz de los bloques emisivos (`emissive_lights`): tiñe lo que los rodea con su color.
+
+use minecraft_diorama::scene_file::parse_scene;
+use minecraft_diorama::{cast_ray, trace_path, RayCone, Radiance, Sampler, Scene};
+use nalgebra::Vector3;
+use std::path::Path;
+
+// Suelo blanco con un bloque que emite luz roja encima y ninguna otra luz
+fn red_lamp_scene(voxel_grid: bool) -> Scene {
+    lamp_scene(voxel_grid, false)
+}
+
+fn lamp_scene(voxel_grid: bool, animated: bool) -> Scene {
+    let source = format!(
+        r#"{{
+            "voxel_grid": {},
+            "emissive_lights": true,
+            "background": [0, 0, 0],
+            "camera": {{ "eye": [0, 6, -10], "center": [0, 0, 0] }},
+            "lights": [],
+            "materials": {{
+                "blanco": {{ "diffuse": [255, 255, 255] }},
+                "lampara": {{ "diffuse": [255, 255, 255], "emission": [255, 0, 0], "animated": {} }}
+            }},
+            "blocks": [
+                {{ "material": "blanco", "fill": {{ "from": [-3, -1, -3], "to": [3, -1, 3] }} }},
+                {{ "material": "lampara", "at": [0, 0, 0] }}
+            ]
+        }}"#,
+        voxel_grid, animated
+    );
+    parse_scene(&source, Path::new(".")).unwrap()
+}
+
+// Rayo hacia el suelo, junto a la lámpara
+fn floor_ray() -> (Vector3<f32>, Vector3<f32>) {
+    (Vector3::new(1.0, 3.0, 0.0), Vector3::new(0.0, -1.0, 0.0))
+}
+
+#[test]
+fn red_emitter_lights_the_floor_red() {
+    for voxel_grid in [false, true] {
+        let scene = red_lamp_scene(voxel_grid);
+        assert_eq!(scene.lights.len(), 1);
+        let (origin, direction) = floor_ray();
+        let color = cast_ray(&origin, &direction, &scene, &scene.lights, 0);
+        assert!(color.r > 0.01, "el suelo no recibe luz: {:?}", color);
+        assert!(color.r > 4.0 * color.g && color.r > 4.0 * color.b, "el suelo no es rojo: {:?}", color);
+    }
+}
+
+// Promedio de varios caminos desde el mismo rayo
+fn average_path(scene: &Scene, samples: u32) -> Radiance {
+    let (origin, direction) = floor_ray();
+    let mut sampler = Sampler::new(7);
+    let mut sum = Radiance::BLACK;
+    for _ in 0..samples {
+        sum += trace_path(&origin, &direction, scene, &scene.lights, &mut sampler, 5, RayCone::POINT);
+    }
+    sum.scale(1.0 / samples as f32)
+}
+
+#[test]
+fn red_emitter_lights_the_floor_red_with_path_tracing() {
+    let color = average_path(&red_lamp_scene(true), 64);
+    assert!(color.r > 0.01, "el suelo no recibe luz: {:?}", color);
+    assert!(color.r > 4.0 * color.g && color.r > 4.0 * color.b, "el suelo no es rojo: {:?}", color);
+}
+
+#[test]
+fn animated_emitters_still_light_the_floor_with_path_tracing() {
+    // Los bloques animados no tienen luz en `lights`: su luz solo llega por los caminos
+    // que rebotan en el suelo y los encuentran
+    let scene = lamp_scene(false, true);
+    assert!(scene.lights.is_empty());
+    let color = average_path(&scene, 512);
+    assert!(color.r > 0.01, "el suelo no recibe luz: {:?}", color);
+    assert_eq!((color.g, color.b), (0.0, 0.0));
+}
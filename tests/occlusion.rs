@@ -0,0 +1,155 @@
+// Consultas de oclusión para los rayos de sombra (`Scene::occluded`).
+// Se prueban las mismas escenas con y sin BVH y con el suelo como cubos o como `VoxelGrid`.
+
+use minecraft_diorama::{Camera, Color, Cube, Light, Material, Scene, VoxelGrid};
+use nalgebra::Vector3;
+use std::sync::Arc;
+
+fn material(refractive_index: f32, albedo: [f32; 4]) -> [Arc<Material>; 6] {
+    let material = Arc::new(Material::new(Color::new(120, 120, 120), 10.0, albedo, refractive_index, false, None));
+    std::array::from_fn(|_| Arc::clone(&material))
+}
+
+fn stone() -> [Arc<Material>; 6] {
+    material(1.0, [0.9, 0.1, 0.0, 0.0])
+}
+
+fn water() -> [Arc<Material>; 6] {
+    material(1.33, [0.5, 0.3, 0.1, 0.5])
+}
+
+fn camera() -> Camera {
+    Camera::new(Vector3::new(0.0, 5.0, 10.0), Vector3::zeros(), Vector3::y())
+}
+
+// Suelo de 9x9 bloques cuya cara de arriba está en y = 0
+fn cube_ground() -> Scene {
+    let mut scene = Scene::new(Vec::new(), Vec::new(), camera());
+    for x in -4..=4 {
+        for z in -4..=4 {
+            scene.add_object(Cube::new(Vector3::new(x as f32, -0.5, z as f32), 1.0, stone()));
+        }
+    }
+    scene
+}
+
+fn voxel_ground() -> Scene {
+    let mut grid = VoxelGrid::new(Vector3::new(-4.5, -1.0, -4.5), [9, 4, 9], 1.0);
+    let id = grid.add_block_type(stone());
+    for x in 0..9 {
+        for z in 0..9 {
+            grid.set(x, 0, z, id);
+        }
+    }
+    let mut scene = Scene::new(Vec::new(), Vec::new(), camera());
+    scene.add_object(grid);
+    scene
+}
+
+// Cada escena en sus dos variantes: recorrido lineal y BVH
+fn variants(build: fn() -> Scene) -> Vec<Scene> {
+    let linear = build();
+    let mut with_bvh = build();
+    with_bvh.build_bvh();
+    vec![linear, with_bvh]
+}
+
+fn grounds() -> Vec<Scene> {
+    let mut scenes = variants(cube_ground);
+    scenes.extend(variants(voxel_ground));
+    scenes
+}
+
+// Rayo de sombra desde justo encima del centro del suelo hacia la luz
+fn shadow_ray(light: &Light) -> (Vector3<f32>, Vector3<f32>, f32) {
+    let origin = Vector3::new(0.0, 1e-2, 0.0);
+    let to_light = light.position - origin;
+    (origin, to_light.normalize(), to_light.magnitude())
+}
+
+#[test]
+fn light_below_the_ground_is_occluded() {
+    // Como la segunda luz del diorama, que queda bajo el suelo
+    let light = Light::new(Vector3::new(-1.0, -100.0, 1.0), Color::new(255, 255, 255), 2.0, 0.0);
+    let (origin, dir, distance) = shadow_ray(&light);
+    for scene in grounds() {
+        assert!(scene.occluded(&origin, &dir, distance));
+    }
+}
+
+#[test]
+fn light_above_the_ground_is_visible() {
+    let light = Light::new(Vector3::new(1.0, 100.0, -1.0), Color::new(255, 255, 255), 2.0, 0.0);
+    let (origin, dir, distance) = shadow_ray(&light);
+    for scene in grounds() {
+        assert!(!scene.occluded(&origin, &dir, distance));
+    }
+}
+
+#[test]
+fn occluders_behind_the_light_are_ignored() {
+    let light = Light::new(Vector3::new(0.0, 5.0, 0.0), Color::new(255, 255, 255), 2.0, 0.0);
+    let (origin, dir, distance) = shadow_ray(&light);
+    for mut scene in variants(cube_ground) {
+        scene.add_object(Cube::new(Vector3::new(0.0, 10.0, 0.0), 1.0, stone()));
+        scene.build_bvh();
+        assert!(!scene.occluded(&origin, &dir, distance));
+        // Pero sí cuenta si la luz está más lejos que el bloque
+        assert!(scene.occluded(&origin, &dir, 20.0));
+    }
+}
+
+#[test]
+fn occluder_between_the_point_and_the_light() {
+    let light = Light::new(Vector3::new(0.0, 50.0, 0.0), Color::new(255, 255, 255), 2.0, 0.0);
+    let (origin, dir, distance) = shadow_ray(&light);
+    for mut scene in variants(voxel_ground) {
+        scene.add_object(Cube::new(Vector3::new(0.0, 3.0, 0.0), 1.0, stone()));
+        scene.build_bvh();
+        assert!(scene.occluded(&origin, &dir, distance));
+    }
+}
+
+#[test]
+fn block_containing_the_origin_does_not_occlude() {
+    // El fondo bajo un bloque de agua: el rayo de sombra arranca dentro del agua
+    let light = Light::new(Vector3::new(0.0, 50.0, 0.0), Color::new(255, 255, 255), 2.0, 0.0);
+    let (origin, dir, distance) = shadow_ray(&light);
+
+    let mut scene = cube_ground();
+    scene.add_object(Cube::new(Vector3::new(0.0, 0.5, 0.0), 1.0, water()));
+    assert!(!scene.occluded(&origin, &dir, distance));
+
+    let mut grid = VoxelGrid::new(Vector3::new(-4.5, -1.0, -4.5), [9, 4, 9], 1.0);
+    let stone_id = grid.add_block_type(stone());
+    let water_id = grid.add_block_type(water());
+    grid.set(4, 0, 4, stone_id);
+    grid.set(4, 1, 4, water_id);
+    let mut scene = Scene::new(Vec::new(), Vec::new(), camera());
+    scene.add_object(grid);
+    assert!(!scene.occluded(&origin, &dir, distance));
+
+    // Un segundo bloque encima sí tapa la luz
+    let mut scene = cube_ground();
+    scene.add_object(Cube::new(Vector3::new(0.0, 0.5, 0.0), 1.0, water()));
+    scene.add_object(Cube::new(Vector3::new(0.0, 1.5, 0.0), 1.0, stone()));
+    assert!(scene.occluded(&origin, &dir, distance));
+}
+
+#[test]
+fn occluded_agrees_with_intersect() {
+    // En el diorama, la consulta de oclusión y la intersección más cercana deben coincidir
+    // para rayos desde encima de los bloques hacia las dos luces (una arriba y otra abajo)
+    let scene = minecraft_diorama::load_scene(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/diorama.json")).unwrap();
+    for light in &scene.lights {
+        for x in -12..=12 {
+            for z in -12..=12 {
+                let origin = Vector3::new(x as f32 * 0.5, 8.0, z as f32 * 0.5);
+                let (dir, distance) = light.direction_from(&origin);
+                let hit = scene.intersect(&origin, &dir);
+                let expected = hit.is_intersecting && hit.distance < distance;
+                assert_eq!(scene.occluded(&origin, &dir, distance), expected, "origen {:?}", origin);
+            }
+        }
+    }
+}